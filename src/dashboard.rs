@@ -0,0 +1,208 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use nannou::prelude::*;
+
+use crate::solver::coevolution::SelfPlayEvaluator;
+use crate::solver::neat;
+
+const CHAMPION_PATH: &str = "connect4_champion.json";
+
+// shared between the training thread and the window
+struct Training {
+	stats: Vec<neat::GenerationStats>,
+	champion: Option<neat::Network>,
+	paused: bool
+}
+
+type SharedTraining = Arc<(Mutex<Training>, Condvar)>;
+
+struct Dashboard {
+	_window_id: window::Id,
+	training: SharedTraining,
+	status: String
+}
+
+// usage: connect_4 train [config file]
+//        connect_4 train --resume [checkpoint file]
+pub fn run() {
+	nannou::app(dashboard).run();
+}
+
+fn dashboard(app: &App) -> Dashboard {
+	let id = app.new_window()
+		.title("training")
+		.size(1000, 750)
+		.view(view)
+		.event(window_event)
+		.build()
+		.unwrap();
+
+	let args: Vec<String> = std::env::args().skip(2).collect();
+	let (neat_connect, evaluator) = crate::start_connect_4(&args);
+	let training = Arc::new((Mutex::new(Training {
+		stats: Vec::new(),
+		champion: None,
+		paused: false
+	}), Condvar::new()));
+	train_in_background(neat_connect, evaluator, training.clone());
+
+	Dashboard {
+		_window_id: id,
+		training,
+		status: String::from("space pauses, s saves the champion")
+	}
+}
+
+// generations run until the app closes. pausing takes effect once the current generation is done
+fn train_in_background(mut neat_connect: neat::Neat, mut evaluator: SelfPlayEvaluator, training: SharedTraining) {
+	thread::spawn(move || {
+		loop {
+			{
+				let (lock, resumed) = &*training;
+				let mut state = lock.lock().unwrap();
+				while state.paused {
+					state = resumed.wait(state).unwrap();
+				}
+			}
+			neat_connect.next_generation(&mut evaluator);
+			crate::save_checkpoint_if_due(&neat_connect);
+			let mut state = training.0.lock().unwrap();
+			state.stats.extend(neat_connect.get_stats().last().cloned());
+			state.champion = neat_connect.get_champion().cloned();
+		}
+	});
+}
+
+fn toggle_pause(model: &mut Dashboard) {
+	let (lock, resumed) = &*model.training;
+	let mut state = lock.lock().unwrap();
+	state.paused = !state.paused;
+	resumed.notify_all();
+	model.status = String::from(if state.paused {"paused"} else {"resumed"});
+}
+
+fn save_champion(model: &mut Dashboard) {
+	let state = model.training.0.lock().unwrap();
+	model.status = match &state.champion {
+		Some(champion) => match champion.save_json(CHAMPION_PATH) {
+			Ok(()) => format!("saved the generation {} champion to {}", state.stats.last().map_or(0, |s| s.generation), CHAMPION_PATH),
+			Err(error) => error.to_string()
+		},
+		None => String::from("no generation has finished yet")
+	};
+}
+
+// the pause and save buttons along the top of the window
+fn get_buttons(win: &Rect) -> (Rect, Rect) {
+	let pause = Rect::from_w_h(120.0, 36.0).top_left_of(win.pad(16.0));
+	let save = pause.right_of(pause).shift_x(16.0);
+	(pause, save)
+}
+
+fn window_event(app: &App, model: &mut Dashboard, event: WindowEvent) {
+	match event {
+		MousePressed(MouseButton::Left) => {
+			let (pause, save) = get_buttons(&app.window_rect());
+			let mouse = app.mouse.position();
+			if pause.contains(mouse) {
+				toggle_pause(model);
+			}
+			else if save.contains(mouse) {
+				save_champion(model);
+			}
+		},
+		KeyPressed(Key::Space) => toggle_pause(model),
+		KeyPressed(Key::S) => save_champion(model),
+		_ => {}
+	}
+}
+
+fn view(app: &App, model: &Dashboard, frame: Frame) {
+	let draw = app.draw();
+	let win = app.window_rect();
+	draw.background().color(rgb(0.1, 0.1, 0.12));
+
+	let state = model.training.0.lock().unwrap();
+	let (pause, save) = get_buttons(&win);
+	draw_button(&draw, &pause, if state.paused {"resume"} else {"pause"});
+	draw_button(&draw, &save, "save champion");
+	let status = format!("generation {} | {}", state.stats.last().map_or(0, |s| s.generation + 1), model.status);
+	draw.text(&status)
+		.xy(pt2(save.right() + 16.0 + 250.0, save.y()))
+		.w(500.0)
+		.left_justify()
+		.color(WHITE)
+		.font_size(14);
+
+	let plot_area = win.pad(16.0).pad_top(68.0);
+	let plot_height = plot_area.h() / 3.0;
+	let plots: Vec<Rect> = (0..3)
+		.map(|i| Rect::from_w_h(plot_area.w(), plot_height - 12.0).mid_top_of(plot_area).shift_y(-plot_height * i as f32))
+		.collect();
+
+	let best: Vec<f32> = state.stats.iter().map(|s| s.best_fitness).collect();
+	let mean: Vec<f32> = state.stats.iter().map(|s| s.mean_fitness).collect();
+	let species: Vec<f32> = state.stats.iter().map(|s| s.num_species as f32).collect();
+	let nodes: Vec<f32> = state.stats.iter().map(|s| s.mean_nodes).collect();
+	let connections: Vec<f32> = state.stats.iter().map(|s| s.mean_connections).collect();
+	draw_plot(&draw, &plots[0], "fitness (best, mean)", &[(&best, ORANGE), (&mean, SKYBLUE)]);
+	draw_plot(&draw, &plots[1], "species", &[(&species, LIGHTGREEN)]);
+	draw_plot(&draw, &plots[2], "mean genome size (connections, nodes)", &[(&connections, VIOLET), (&nodes, GRAY)]);
+
+	draw.to_frame(app, &frame).unwrap();
+}
+
+fn draw_button(draw: &Draw, rect: &Rect, label: &str) {
+	draw.rect()
+		.xy(rect.xy())
+		.wh(rect.wh())
+		.color(rgb(0.25, 0.25, 0.3))
+		.stroke(WHITE)
+		.stroke_weight(1.0);
+	draw.text(label)
+		.xy(rect.xy())
+		.color(WHITE)
+		.font_size(14);
+}
+
+// every series shares the plot's vertical scale, which always includes 0
+fn draw_plot(draw: &Draw, rect: &Rect, title: &str, series: &[(&[f32], Srgb<u8>)]) {
+	draw.rect()
+		.xy(rect.xy())
+		.wh(rect.wh())
+		.color(rgb(0.15, 0.15, 0.18));
+
+	let max = series.iter()
+		.flat_map(|(values, _)| values.iter().copied())
+		.filter(|value| value.is_finite())
+		.fold(0.0f32, f32::max);
+	let min = series.iter()
+		.flat_map(|(values, _)| values.iter().copied())
+		.filter(|value| value.is_finite())
+		.fold(0.0f32, f32::min);
+	let range = if max > min {max - min} else {1.0};
+
+	draw.text(&format!("{}  [{:.2}, {:.2}]", title, min, max))
+		.xy(rect.pad(8.0).mid_top())
+		.w(rect.w() - 16.0)
+		.left_justify()
+		.color(WHITE)
+		.font_size(13);
+
+	let area = rect.pad(8.0).pad_top(24.0);
+	for (values, color) in series {
+		if values.len() < 2 {
+			continue;
+		}
+		let points = values.iter().enumerate().map(|(i, &value)| {
+			let x = area.left() + area.w() * i as f32 / (values.len() - 1) as f32;
+			let y = area.bottom() + area.h() * (value - min) / range;
+			pt2(x, y)
+		});
+		draw.polyline()
+			.weight(2.0)
+			.points(points)
+			.color(*color);
+	}
+}
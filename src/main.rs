@@ -1,23 +1,196 @@
+mod dashboard;
 mod solver;
 
 use nannou::prelude::*;
 use nannou::state::mouse::Mouse;
 use solver::game::Game;
 use solver::Solver;
+use solver::benchmark;
+use solver::coevolution;
 use solver::neat;
 
+const CHECKPOINT_PATH: &str = "connect4.ckpt";
+const CHECKPOINT_INTERVAL: usize = 10;
+const STATS_PATH: &str = "connect4_stats.csv";
+const ANALYSIS_NODE_LIMIT: u64 = 10_000_000;
 
-
+// usage: connect_4 [xor|connect4|train] [config file]
+//        connect_4 [connect4|train] --resume [checkpoint file]
+//        connect_4 benchmark [genome file]
+//        connect_4 play [genome file]
+//        connect_4 solve [moves] [--weak]
+//        connect_4 analyze [moves] [node budget]
 fn main() {
-	let mut neat_test = neat::Neat::new(2, 1);
-	neat_test.generate_population(10);
-	neat_test.calculate_fitnesses(calculate_fitness);
-	// nannou::app(model)
-	// 	.view(view)
-	// 	.run();
+	let args: Vec<String> = std::env::args().collect();
+	match args.get(1).map(|mode| mode.as_str()) {
+		// moves are columns numbered from 1, e.g. 4453. a weak solve only finds who wins
+		Some("solve") => {
+			let weak = args[2..].iter().any(|option| option == "--weak");
+			let moves = args[2..].iter().find(|option| *option != "--weak");
+			solve_position(moves.map_or("", |moves| moves.as_str()), weak);
+			return;
+		},
+		// the negamax solver's view of a position, searched as deep as the node budget allows
+		Some("analyze") => {
+			let node_limit = args.get(3).map_or(ANALYSIS_NODE_LIMIT, |nodes| {
+				nodes.parse().unwrap_or_else(|_| panic!("{} is not a node budget", nodes))
+			});
+			analyze_position(args.get(2).map_or("", |moves| moves.as_str()), node_limit);
+			return;
+		},
+		Some("benchmark") => {
+			benchmark_champion(args.get(2).map_or("connect4_champion.json", |path| path.as_str()));
+			return;
+		},
+		// without a genome the computer player is the negamax solver
+		Some("play") => {
+			nannou::app(model).run();
+			return;
+		},
+		// trains connect 4 players in the background while plotting progress
+		Some("train") => {
+			dashboard::run();
+			return;
+		},
+		_ => {}
+	}
+
+	match args.get(1).map(|mode| mode.as_str()) {
+		Some("connect4") => evolve_connect_4(start_connect_4(&args[2..])),
+		_ => evolve_xor(load_config(args.get(2).map(|path| path.as_str())))
+	}
+}
+
+fn load_config(path: Option<&str>) -> neat::NeatConfig {
+	match path {
+		Some(path) => neat::NeatConfig::from_file(path),
+		None => neat::NeatConfig::builder().target_species(Some(10)).build()
+	}.unwrap_or_else(|error| panic!("bad NEAT config: {}", error))
+}
+
+fn evolve_xor(config: neat::NeatConfig) {
+	let mut neat_test = neat::Neat::with_config(2, 1, config).unwrap_or_else(|error| panic!("bad NEAT config: {}", error));
+	neat_test.add_reporter(print_stats);
+	match neat::StatsLogger::create("xor_stats.jsonl", neat::LogFormat::JsonLines) {
+		Ok(logger) => neat_test.add_reporter(logger),
+		Err(error) => println!("could not create stats log: {}", error)
+	}
+	neat_test.generate_population(150);
+	for _ in 0..100 {
+		neat_test.next_generation(&mut neat::ParallelEvaluator(calculate_fitness));
+	}
 }
 
-fn reverse_mean_square_error(target: &Vec<f32>, output: Vec<f32>) -> f32 {
+// a new run from an optional config file, or with --resume the run saved in a checkpoint
+fn start_connect_4(options: &[String]) -> (neat::Neat, coevolution::SelfPlayEvaluator) {
+	let neat_connect = match options.first().map(|option| option.as_str()) {
+		Some("--resume") => {
+			let path = options.get(1).map_or(CHECKPOINT_PATH, |path| path.as_str());
+			neat::Neat::load_checkpoint(path).unwrap_or_else(|error| panic!("could not resume from {}: {}", path, error))
+		},
+		config_path => new_connect_4_neat(load_config(config_path))
+	};
+	let evaluator = coevolution::SelfPlayEvaluator::new(Some(10), 0.05, neat_connect.get_seed());
+	(neat_connect, evaluator)
+}
+
+fn new_connect_4_neat(config: neat::NeatConfig) -> neat::Neat {
+	let mut neat_connect = neat::Neat::with_config(coevolution::NUM_SENSORS, coevolution::NUM_OUTPUTS, config)
+		.unwrap_or_else(|error| panic!("bad NEAT config: {}", error));
+	neat_connect.generate_population(100);
+	neat_connect
+}
+
+// connect 4 runs are saved every few generations so they can survive restarts
+fn save_checkpoint_if_due(neat_connect: &neat::Neat) {
+	if neat_connect.get_generation().is_multiple_of(CHECKPOINT_INTERVAL) {
+		neat_connect.save_checkpoint(CHECKPOINT_PATH).unwrap_or_else(|error| println!("{}", error));
+	}
+}
+
+fn evolve_connect_4((mut neat_connect, mut evaluator): (neat::Neat, coevolution::SelfPlayEvaluator)) {
+	neat_connect.add_reporter(print_stats);
+	// a resumed run carries on with the log of the run it continues
+	let logger = if neat_connect.get_generation() > 0 {
+		neat::StatsLogger::append(STATS_PATH, neat::LogFormat::Csv)
+	}
+	else {
+		neat::StatsLogger::create(STATS_PATH, neat::LogFormat::Csv)
+	};
+	match logger {
+		Ok(logger) => neat_connect.add_reporter(logger),
+		Err(error) => println!("could not create stats log: {}", error)
+	}
+	for _ in 0..50 {
+		neat_connect.next_generation(&mut evaluator);
+		save_checkpoint_if_due(&neat_connect);
+	}
+
+	if let Some(champion) = neat_connect.get_champion() {
+		champion.save_json("connect4_champion.json").unwrap_or_else(|error| println!("{}", error));
+		champion.save_binary("connect4_champion.bin").unwrap_or_else(|error| println!("{}", error));
+		champion.save_dot("connect4_champion.dot").unwrap_or_else(|error| println!("{}", error));
+	}
+}
+
+fn print_stats(stats: &neat::GenerationStats) {
+	println!(
+		"generation {}: best {:.3}, mean {:.3}, {} species, {:.1} connections",
+		stats.generation, stats.best_fitness, stats.mean_fitness, stats.num_species, stats.mean_connections
+	);
+}
+
+fn benchmark_champion(path: &str) {
+	let champion = neat::Network::load(path, coevolution::NUM_SENSORS, coevolution::NUM_OUTPUTS)
+		.unwrap_or_else(|error| panic!("could not load {}: {}", path, error));
+	let report = benchmark::SolverBenchmark::new(4, 5, 0).run(&champion);
+	for result in &report.results {
+		println!("depth {}: {} wins, {} draws, {} losses", result.depth, result.wins, result.draws, result.losses);
+	}
+	println!("score {}", report.score);
+}
+
+// the moves must leave a game that isn't over
+fn play_moves(moves: &str) -> Game {
+	let mut game = Game::new();
+	for (i, column) in moves.chars().enumerate() {
+		let column = match column.to_digit(10) {
+			Some(column @ 1..=7) if game.can_play(column as usize - 1) => column as usize - 1,
+			_ => panic!("move {} ({}) is not a playable column", i + 1, column)
+		};
+		if game.play_piece(column) || game.check_for_tie() {
+			panic!("the game is over after move {}", i + 1);
+		}
+	}
+	game
+}
+
+fn analyze_position(moves: &str, node_limit: u64) {
+	let game = play_moves(moves);
+	let mut negamax_solver = Solver::new(solver::MAX_DEPTH);
+	negamax_solver.set_node_limit(Some(node_limit));
+	println!("{}", negamax_solver.analyze(&game));
+}
+
+fn solve_position(moves: &str, weak: bool) {
+	let game = play_moves(moves);
+	let mut perfect_solver = solver::perfect::PerfectSolver::new();
+	let start = std::time::Instant::now();
+	if weak {
+		println!("{:?} for player {} in {:?}", perfect_solver.solve_weak(&game), game.get_turn(), start.elapsed());
+		return;
+	}
+	let solution = perfect_solver.solve(&game);
+	println!(
+		"{:?} for player {} (score {}) in {} moves, {} nodes in {:?}",
+		solution.value, game.get_turn(), solution.score, solution.moves_to_end, solution.nodes, start.elapsed()
+	);
+	if let Some(column) = perfect_solver.get_best_move(&game) {
+		println!("best move: column {}", column + 1);
+	}
+}
+
+fn reverse_mean_square_error(target: &[f32], output: &[f32]) -> f32 {
 	let mut acc = 0.0;
 	for i in 0..target.len() {
 		acc += 1.0 - (target[i] - output[i]) * (target[i] - output[i]);
@@ -32,9 +205,12 @@ fn calculate_fitness(network: &mut neat::Network) -> f32 {
 		(vec![1.0, 1.0], vec![0.0])
 	];
 
+	let mut compiled = network.compile().unwrap();
+	let mut output = vec![0.0; 1];
 	let mut total_fitness = 0.0;
 	for data in data_vec {
-		total_fitness += reverse_mean_square_error(&data.1, network.feed_forward(&data.0));
+		compiled.activate(&data.0, &mut output);
+		total_fitness += reverse_mean_square_error(&data.1, &output);
 	}
 
 	total_fitness
@@ -46,6 +222,8 @@ struct Model {
 	_window_id: window::Id,
 	connect: Game,
 	solver: Solver,
+	network: Option<(neat::Network, neat::CompiledNetwork)>, // plays instead of the solver when loaded
+	node_values: Vec<f32>, // what the network saw when it last moved
 	game_over: bool,
 	ai_turn: i32
 }
@@ -53,26 +231,61 @@ struct Model {
 fn model(app: &App) -> Model {
 	let id = app.new_window()
 		.size(900, 775)
+		.view(view)
 		.event(window_event)
 		.build()
 		.unwrap();
+
+	let network = std::env::args().nth(2).map(|path| {
+		let network = neat::Network::load(&path, coevolution::NUM_SENSORS, coevolution::NUM_OUTPUTS)
+			.unwrap_or_else(|error| panic!("could not load {}: {}", path, error));
+		let compiled = network.compile().expect("network can't be compiled");
+		(network, compiled)
+	});
+	if network.is_some() {
+		app.new_window()
+			.title("network")
+			.size(900, 600)
+			.view(view_network)
+			.build()
+			.unwrap();
+	}
 	
     let mut model = Model {
 		_window_id: id,
 		connect: Game::new(),
-		solver: Solver::new(11),
+		solver: Solver::with_time_limit(std::time::Duration::from_secs(1)),
+		node_values: Vec::new(),
+		network,
 		game_over: false,
 		ai_turn: 2
 	};
 
 	if model.ai_turn == 1 {
-		let best_move = model.solver.negamax(&model.connect);
+		let best_move = get_ai_move(&mut model);
 		model.connect.play_piece(best_move);
 	}
 
 	model
 }
 
+fn get_ai_move(model: &mut Model) -> usize {
+	match &mut model.network {
+		Some((_, compiled)) => {
+			let mut sensors = [0.0; coevolution::NUM_SENSORS];
+			let mut outputs = [0.0; coevolution::NUM_OUTPUTS];
+			let (column, _) = coevolution::choose_move(compiled, &model.connect, &mut sensors, &mut outputs);
+			model.node_values = compiled.get_values().to_vec();
+			column
+		},
+		None => {
+			let analysis = model.solver.analyze(&model.connect);
+			println!("{}", analysis);
+			analysis.choose_move(&mut rand::thread_rng())
+		}
+	}
+}
+
 fn window_event(app: &App, model: &mut Model, event: WindowEvent) {
 	match event {
 		MousePressed(button) => {
@@ -81,7 +294,7 @@ fn window_event(app: &App, model: &mut Model, event: WindowEvent) {
 				model.game_over = model.connect.play_piece(column);
 				
 				if !model.game_over && model.connect.get_turn() == model.ai_turn {
-					let best_move = model.solver.negamax(&model.connect);
+					let best_move = get_ai_move(model);
 					model.game_over = model.connect.play_piece(best_move);
 				}
 			}
@@ -100,6 +313,17 @@ fn view(app: &App, model: &Model, frame: Frame) {
 }
 
 
+fn view_network(app: &App, model: &Model, frame: Frame) {
+	let draw = app.draw();
+	let win = app.window_rect();
+
+	draw.background().color(BLACK);
+	if let Some((network, _)) = &model.network {
+		draw_network(&draw, &win, network, &model.node_values);
+	}
+
+	draw.to_frame(app, &frame).unwrap();
+}
 
 fn draw_board(mouse: &Mouse, draw: &Draw, win: &Rect, model: &Model) {
 	draw.rect()
@@ -151,3 +375,116 @@ fn draw_board(mouse: &Mouse, draw: &Draw, win: &Rect, model: &Model) {
 		}
 	}
 }
+
+// sensors and the bias along the bottom, outputs along the top and hidden nodes in between,
+// each hidden node one row above the highest node feeding it
+fn layout_network(network: &neat::Network, win: &Rect) -> Vec<Point2> {
+	let nodes = network.get_nodes();
+	let mut positions = std::collections::HashMap::with_capacity(nodes.len());
+	for (i, node) in nodes.iter().enumerate() {
+		positions.insert(node.get_innovation(), i);
+	}
+
+	let mut rows = vec![0; nodes.len()];
+	for (i, node) in nodes.iter().enumerate() {
+		if node.get_type() == neat::Type::Hidden {
+			rows[i] = 1;
+		}
+	}
+	// bounded so recurrent loops can't keep pushing nodes up
+	for _ in 0..nodes.len() {
+		let mut changed = false;
+		for connection in network.get_connections().iter().filter(|c| c.is_enabled()) {
+			let (input, output) = (positions[&connection.get_input()], positions[&connection.get_output()]);
+			if nodes[output].get_type() == neat::Type::Hidden && rows[output] <= rows[input] && rows[input] < nodes.len() {
+				rows[output] = rows[input] + 1;
+				changed = true;
+			}
+		}
+		if !changed {
+			break;
+		}
+	}
+	let top_row = nodes.iter().enumerate()
+		.filter(|(_, node)| node.get_type() == neat::Type::Hidden)
+		.map(|(i, _)| rows[i])
+		.max()
+		.unwrap_or(0) + 1;
+	for (i, node) in nodes.iter().enumerate() {
+		if node.get_type() == neat::Type::Output {
+			rows[i] = top_row;
+		}
+	}
+
+	let area = win.pad(40.0);
+	let mut row_members = vec![Vec::new(); top_row + 1];
+	for i in 0..nodes.len() {
+		row_members[rows[i]].push(i);
+	}
+	let mut points = vec![pt2(0.0, 0.0); nodes.len()];
+	for (row, members) in row_members.iter().enumerate() {
+		let y = area.bottom() + area.h() * row as f32 / top_row as f32;
+		for (k, &i) in members.iter().enumerate() {
+			let x = area.left() + area.w() * (k as f32 + 0.5) / members.len() as f32;
+			points[i] = pt2(x, y);
+		}
+	}
+	points
+}
+
+// positive values and weights are green, negative ones red
+fn signed_color(value: f32) -> Rgb {
+	let strength = value.abs().min(1.0);
+	if value >= 0.0 {
+		rgb(0.2, 0.2 + 0.8 * strength, 0.2)
+	}
+	else {
+		rgb(0.2 + 0.8 * strength, 0.2, 0.2)
+	}
+}
+
+fn draw_network(draw: &Draw, win: &Rect, network: &neat::Network, node_values: &[f32]) {
+	let nodes = network.get_nodes();
+	let points = layout_network(network, win);
+	let mut positions = std::collections::HashMap::with_capacity(nodes.len());
+	for (i, node) in nodes.iter().enumerate() {
+		positions.insert(node.get_innovation(), i);
+	}
+
+	for connection in network.get_connections() {
+		let start = points[positions[&connection.get_input()]];
+		let end = points[positions[&connection.get_output()]];
+		if connection.is_enabled() {
+			draw.line()
+				.start(start)
+				.end(end)
+				.weight(0.5 + connection.weight.abs().min(4.0))
+				.color(signed_color(connection.weight));
+		}
+		else {
+			draw.line()
+				.start(start)
+				.end(end)
+				.weight(0.5)
+				.color(rgb(0.3, 0.3, 0.3));
+		}
+	}
+
+	for (i, node) in nodes.iter().enumerate() {
+		let value = node_values.get(i).copied().unwrap_or(0.0);
+		let radius = if node.get_type() == neat::Type::Output {14.0} else {8.0};
+		draw.ellipse()
+			.xy(points[i])
+			.radius(radius)
+			.color(signed_color(value))
+			.stroke(WHITE)
+			.stroke_weight(1.0);
+		if node.get_type() == neat::Type::Output {
+			let column = node.get_innovation() - coevolution::NUM_SENSORS - 1;
+			draw.text(&format!("{}\n{:.2}", column, value))
+				.xy(points[i] + vec2(0.0, 28.0))
+				.color(WHITE)
+				.font_size(12);
+		}
+	}
+}
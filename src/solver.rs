@@ -1,44 +1,195 @@
+pub mod analysis;
+pub mod benchmark;
+pub mod coevolution;
 pub mod game;
 pub mod neat;
+pub mod perfect;
+pub mod transposition;
 
-use game::Game;
+use analysis::{Analysis, ColumnAnalysis};
+use game::{Game, COLUMN_ORDER};
+use perfect::GameValue;
 use neat::Neat;
 use rand::Rng;
+use std::time::{Duration, Instant};
+use transposition::{Bound, ReplacementPolicy, TranspositionTable};
+
+// default memory for the transposition table, enough for depth 11+ searches in the GUI
+pub const DEFAULT_TABLE_MEMORY: usize = 64 << 20;
+
+// deep enough to reach the end of any game
+pub const MAX_DEPTH: u32 = 42;
+
+// how many nodes are searched between checks of the clock
+const TIME_CHECK_INTERVAL: u64 = 1024;
 
 pub struct Solver {
-	search_depth: u32
+	search_depth: u32,
+	table: TranspositionTable,
+	move_ordering: bool,
+	killer_moves: Vec<[Option<usize>; 2]>, // the last two moves to cause a cutoff at each ply
+	nodes: u64,
+	time_limit: Option<Duration>, // per move
+	node_limit: Option<u64>, // per move
+	start: Instant,
+	iteration_depth: u32,
+	completed_depth: u32,
+	aborted: bool // the budget ran out during the current iteration
 }
 
 impl Solver {
 	pub fn new(depth: u32) -> Solver {
+		Solver::with_table(depth, DEFAULT_TABLE_MEMORY, ReplacementPolicy::DepthPreferred)
+	}
+
+	pub fn with_table(depth: u32, table_memory: usize, policy: ReplacementPolicy) -> Solver {
 		Solver {
-			search_depth: depth
+			search_depth: depth,
+			table: TranspositionTable::new(table_memory, policy),
+			move_ordering: true,
+			killer_moves: vec![[None; 2]; depth as usize],
+			nodes: 0,
+			time_limit: None,
+			node_limit: None,
+			start: Instant::now(),
+			iteration_depth: 0,
+			completed_depth: 0,
+			aborted: false
 		}
 	}
 
-	pub fn negamax(self: &Solver, game: &Game) -> usize {
-		let moves = game.get_moves();
-		
-		let mut scores = Vec::new();
-		for node in moves {
-			scores.push((node.0, -self.negamax_rec(&node.1, f32::NEG_INFINITY, f32::INFINITY, self.search_depth - 1)));
-		}
-		println!("{:?}", scores);
+	// searches as deep as it can in the given time for each move
+	pub fn with_time_limit(time_limit: Duration) -> Solver {
+		let mut solver = Solver::new(MAX_DEPTH);
+		solver.set_time_limit(Some(time_limit));
+		solver
+	}
+
+	// with a time or node limit the search deepens one ply at a time up to the search depth,
+	// and plays the best move of the deepest search that finished within the budget
+	pub fn set_time_limit(&mut self, time_limit: Option<Duration>) {
+		self.time_limit = time_limit;
+	}
+
+	pub fn set_node_limit(&mut self, node_limit: Option<u64>) {
+		self.node_limit = node_limit;
+	}
+
+	// without ordering columns are searched left to right, which is useful for measuring how much it prunes
+	#[cfg(test)]
+	pub fn set_move_ordering(&mut self, move_ordering: bool) {
+		self.move_ordering = move_ordering;
+	}
+
+	// ties between equally scored moves are broken with the given rng
+	pub fn negamax_with_rng<R: Rng>(self: &mut Solver, game: &Game, rng: &mut R) -> usize {
+		self.analyze(game).choose_move(rng)
+	}
+
+	// scores every playable column, the game must not be over
+	pub fn analyze(self: &mut Solver, game: &Game) -> Analysis {
+		self.table.new_search();
+		self.nodes = 0;
+		self.start = Instant::now();
+		self.completed_depth = 0;
+		self.aborted = false;
 
-		let mut best_moves = vec![scores[0]];
-		for i in 1..scores.len() {
-			if scores[i].1 > best_moves[0].1 {
-				best_moves = vec![scores[i]];
+		// searching past the end of the game changes nothing
+		let remaining_moves = (42 - game.get_move_count()) as u32;
+		let max_depth = self.search_depth.min(remaining_moves).max(1);
+		let limited = self.time_limit.is_some() || self.node_limit.is_some();
+		let mut columns = Vec::new();
+		for depth in if limited {1} else {max_depth}..=max_depth {
+			let iteration = self.score_moves(game, depth);
+			if self.aborted {
+				break;
 			}
-			else if scores[i].1 == best_moves[0].1 {
-				best_moves.push(scores[i]);
+			columns = iteration.into_iter()
+				.map(|(column, score)| ColumnAnalysis {
+					column,
+					score,
+					principal_variation: self.get_principal_variation(game, column, depth)
+				})
+				.collect();
+			self.completed_depth = depth;
+			if columns.iter().any(|c| c.score == f32::INFINITY) {
+				break; // a forced win won't get any better
 			}
 		}
 
-		best_moves[rand::thread_rng().gen_range(0, best_moves.len())].0
+		let mut analysis = Analysis {
+			columns,
+			depth: self.completed_depth,
+			nodes: self.nodes,
+			elapsed: self.start.elapsed(),
+			forced: None
+		};
+		let best_score = analysis.get_best_score();
+		analysis.forced = if best_score == f32::INFINITY {
+			Some(GameValue::Win)
+		}
+		else if best_score == f32::NEG_INFINITY {
+			Some(GameValue::Loss)
+		}
+		else if self.completed_depth >= remaining_moves {
+			Some(GameValue::Draw)
+		}
+		else {
+			None
+		};
+		analysis
+	}
+
+	// follows the best moves stored in the transposition table after playing the column
+	fn get_principal_variation(self: &Solver, game: &Game, column: usize, depth: u32) -> Vec<usize> {
+		let mut line = vec![column];
+		let mut position = game.copy();
+		let mut game_over = position.play_piece(column) || position.check_for_tie();
+		while !game_over && (line.len() as u32) < depth {
+			let next = match self.table.get(position.get_key()).and_then(|entry| entry.best_move) {
+				Some(next) if position.can_play(next as usize) => next as usize,
+				_ => break
+			};
+			line.push(next);
+			game_over = position.play_piece(next) || position.check_for_tie();
+		}
+		line
+	}
+
+	fn score_moves(self: &mut Solver, game: &Game, depth: u32) -> Vec<(usize, f32)> {
+		self.iteration_depth = depth;
+		for killers in &mut self.killer_moves {
+			*killers = [None; 2];
+		}
+
+		let mut scores = Vec::new();
+		for node in game.get_moves() {
+			scores.push((node.0, -self.negamax_rec(&node.1, f32::NEG_INFINITY, f32::INFINITY, depth - 1)));
+		}
+		scores
+	}
+
+	// the first iteration always finishes so there is a move to play
+	fn check_budget(self: &mut Solver) {
+		if self.iteration_depth <= 1 || self.aborted {
+			return;
+		}
+		if self.node_limit.is_some_and(|limit| self.nodes >= limit) {
+			self.aborted = true;
+		}
+		if let Some(time_limit) = self.time_limit {
+			if self.nodes.is_multiple_of(TIME_CHECK_INTERVAL) && self.start.elapsed() >= time_limit {
+				self.aborted = true;
+			}
+		}
 	}
 
-	fn negamax_rec(self: &Solver, game: &Game, mut alpha: f32, beta: f32, mut depth: u32) -> f32 {
+	fn negamax_rec(self: &mut Solver, game: &Game, mut alpha: f32, mut beta: f32, depth: u32) -> f32 {
+		self.nodes += 1;
+		self.check_budget();
+		if self.aborted {
+			return 0.0;
+		}
 		if game.check_for_win() {
 			return f32::NEG_INFINITY;
 		}
@@ -48,23 +199,142 @@ impl Solver {
 		if depth == 0 {
 			return game.get_heuristic();
 		}
-		if alpha >= beta {
-			return beta;
+
+		// a stored result from at least as deep a search can settle the position or narrow the window
+		let original_alpha = alpha;
+		let key = game.get_key();
+		let mut hash_move = None;
+		if let Some(entry) = self.table.get(key) {
+			hash_move = entry.best_move.map(|column| column as usize);
+			if entry.depth >= depth {
+				match entry.bound {
+					Bound::Exact => return entry.score,
+					Bound::Lower => alpha = alpha.max(entry.score),
+					Bound::Upper => beta = beta.min(entry.score)
+				}
+				if alpha >= beta {
+					return entry.score;
+				}
+			}
 		}
-		depth -= 1;
 
-		let nodes = game.get_nodes();
+		let ply = (self.iteration_depth - depth) as usize;
+		let moves = if self.move_ordering {self.get_ordered_moves(game, hash_move, ply)} else {game.get_moves()};
 
-		for node in nodes {
-			let value = -self.negamax_rec(&node, -beta, -alpha, depth);
-			if value >= beta {
-				return value;
+		let mut best_score = f32::NEG_INFINITY;
+		let mut best_move = None;
+		for (column, node) in moves {
+			let value = -self.negamax_rec(&node, -beta, -alpha, depth - 1);
+			if self.aborted {
+				return 0.0; // the result is thrown away, and must not go in the table
+			}
+			if best_move.is_none() || value > best_score {
+				best_score = value;
+				best_move = Some(column);
 			}
 			if value > alpha {
 				alpha = value;
 			}
+			if alpha >= beta {
+				let killers = &mut self.killer_moves[ply];
+				if killers[0] != Some(column) {
+					killers[1] = killers[0];
+					killers[0] = Some(column);
+				}
+				break;
+			}
 		}
 
-		alpha
+		let bound = if best_score <= original_alpha {
+			Bound::Upper
+		}
+		else if best_score >= beta {
+			Bound::Lower
+		}
+		else {
+			Bound::Exact
+		};
+		self.table.store(key, best_score, depth, bound, best_move);
+
+		best_score
+	}
+
+	// the transposition table's best move, then this ply's killer moves, then the rest
+	// by how many threats they make with ties going to the centre
+	fn get_ordered_moves(self: &Solver, game: &Game, hash_move: Option<usize>, ply: usize) -> Vec<(usize, Game)> {
+		let possible = game.get_possible_moves();
+		let killers = self.killer_moves[ply];
+		let mut ranked = Vec::with_capacity(7);
+		for (order, &column) in COLUMN_ORDER.iter().enumerate() {
+			let move_bit = possible & Game::column_mask(column);
+			if move_bit == 0 {
+				continue;
+			}
+			let priority = if Some(column) == hash_move {
+				2
+			}
+			else if killers.contains(&Some(column)) {
+				1
+			}
+			else {
+				0
+			};
+			ranked.push((priority, game.get_threat_score(move_bit), order, column, move_bit));
+		}
+		ranked.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+
+		ranked.into_iter()
+			.map(|(_, _, _, column, move_bit)| {
+				let mut node = game.copy();
+				node.play_move(move_bit);
+				(column, node)
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const POSITIONS: [&[usize]; 4] = [&[], &[3, 3, 3, 3], &[3, 2, 4, 2, 2, 4], &[0, 6, 1, 5, 6, 0, 2]];
+
+	fn play_moves(moves: &[usize]) -> Game {
+		let mut game = Game::new();
+		for &column in moves {
+			game.play_piece(column);
+		}
+		game
+	}
+
+	fn get_scores(analysis: &Analysis) -> Vec<(usize, f32)> {
+		analysis.columns.iter().map(|c| (c.column, c.score)).collect()
+	}
+
+	// a single entry table hardly ever hits, so this compares against searching without one
+	#[test]
+	fn transposition_table_keeps_scores() {
+		for moves in POSITIONS {
+			let game = play_moves(moves);
+			let mut with_table = Solver::with_table(7, 1 << 20, ReplacementPolicy::DepthPreferred);
+			let mut without_table = Solver::with_table(7, 1, ReplacementPolicy::AlwaysReplace);
+			let (with_table, without_table) = (with_table.analyze(&game), without_table.analyze(&game));
+			assert_eq!(get_scores(&with_table), get_scores(&without_table), "{:?}", moves);
+			assert!(with_table.nodes < without_table.nodes, "{:?}", moves);
+		}
+	}
+
+	#[test]
+	fn move_ordering_keeps_scores_and_prunes_more() {
+		for moves in POSITIONS {
+			let game = play_moves(moves);
+			let mut ordered = Solver::with_table(7, 1 << 20, ReplacementPolicy::DepthPreferred);
+			let mut unordered = Solver::with_table(7, 1 << 20, ReplacementPolicy::DepthPreferred);
+			unordered.set_move_ordering(false);
+
+			let (ordered, unordered) = (ordered.analyze(&game), unordered.analyze(&game));
+			assert_eq!(get_scores(&ordered), get_scores(&unordered), "{:?}", moves);
+			assert!(ordered.nodes < unordered.nodes, "{:?}", moves);
+		}
 	}
 }
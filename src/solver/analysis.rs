@@ -0,0 +1,60 @@
+use std::fmt;
+use std::time::Duration;
+
+use rand::Rng;
+
+use super::perfect::GameValue;
+
+#[derive(Clone, Debug)]
+pub struct ColumnAnalysis {
+	pub column: usize,
+	pub score: f32, // from the point of view of the player to move, infinite when the game is decided
+	pub principal_variation: Vec<usize> // the expected line of play, starting with this column
+}
+
+// the result of the deepest search that finished
+#[derive(Clone, Debug)]
+pub struct Analysis {
+	pub columns: Vec<ColumnAnalysis>, // every playable column
+	pub depth: u32,
+	pub nodes: u64,
+	pub elapsed: Duration,
+	// the outcome for the player to move when the search saw it forced: a win or loss found
+	// before the end of the board, or any result when it searched to the end of the board
+	pub forced: Option<GameValue>
+}
+
+impl Analysis {
+	pub fn get_best_score(&self) -> f32 {
+		self.columns.iter().map(|c| c.score).fold(f32::NEG_INFINITY, f32::max)
+	}
+
+	pub fn get_best_columns(&self) -> Vec<usize> {
+		let best_score = self.get_best_score();
+		self.columns.iter()
+			.filter(|c| c.score == best_score)
+			.map(|c| c.column)
+			.collect()
+	}
+
+	// ties between equally scored columns are broken with the given rng
+	pub fn choose_move<R: Rng>(&self, rng: &mut R) -> usize {
+		let best_columns = self.get_best_columns();
+		best_columns[rng.gen_range(0, best_columns.len())]
+	}
+}
+
+impl fmt::Display for Analysis {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "depth {}, {} nodes in {:.3}s", self.depth, self.nodes, self.elapsed.as_secs_f64())?;
+		if let Some(forced) = self.forced {
+			write!(f, ", forced {:?}", forced)?;
+		}
+		for column in &self.columns {
+			let line: Vec<String> = column.principal_variation.iter().map(|c| c.to_string()).collect();
+			let score = if column.score == 0.0 {0.0} else {column.score}; // negated draws print as -0
+			write!(f, "\n  column {}: {} [{}]", column.column, score, line.join(" "))?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,136 @@
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use super::Solver;
+use super::coevolution::{self, NUM_OUTPUTS, NUM_SENSORS};
+use super::game::Game;
+use super::neat::fitness::evaluate_in_parallel;
+use super::neat::{mix_seed, CompiledNetwork, FitnessEvaluator, Network};
+use super::transposition::ReplacementPolicy;
+
+// benchmark searches are shallow, and a solver is made for every depth of every run
+const TABLE_MEMORY: usize = 1 << 20;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DepthResult {
+	pub depth: u32,
+	pub wins: u32,
+	pub draws: u32,
+	pub losses: u32
+}
+
+impl DepthResult {
+	pub fn get_games(&self) -> u32 {
+		self.wins + self.draws + self.losses
+	}
+
+	// 1 for a win and 0.5 for a draw, as a fraction of the games played
+	pub fn get_score(&self) -> f32 {
+		if self.get_games() == 0 {
+			return 0.0;
+		}
+		(self.wins as f32 + 0.5 * self.draws as f32) / self.get_games() as f32
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct BenchmarkReport {
+	pub results: Vec<DepthResult>,
+	pub score: f32 // between 0 and 1, deeper searches count for more
+}
+
+// plays a network against the negamax solver at each depth, as both first and second player
+pub struct SolverBenchmark {
+	depths: Vec<u32>,
+	games_per_side: u32, // the solver breaks ties at random so repeated games can differ
+	seed: u64
+}
+
+impl SolverBenchmark {
+	// depths double from 1 up to max_depth
+	pub fn new(max_depth: u32, games_per_side: u32, seed: u64) -> SolverBenchmark {
+		let mut depths = Vec::new();
+		let mut depth = 1;
+		while depth <= max_depth {
+			depths.push(depth);
+			depth *= 2;
+		}
+		SolverBenchmark::with_depths(depths, games_per_side, seed)
+	}
+
+	pub fn with_depths(depths: Vec<u32>, games_per_side: u32, seed: u64) -> SolverBenchmark {
+		assert!(depths.iter().all(|&depth| depth > 0), "solver depths must be at least 1");
+		SolverBenchmark {
+			depths,
+			games_per_side,
+			seed
+		}
+	}
+
+	pub fn run(&self, network: &Network) -> BenchmarkReport {
+		let mut compiled = network.compile().expect("network can't be compiled");
+		let mut results = Vec::with_capacity(self.depths.len());
+		let mut score = 0.0;
+		let mut total_weight = 0.0;
+		for (i, &depth) in self.depths.iter().enumerate() {
+			let mut solver = Solver::with_table(depth, TABLE_MEMORY, ReplacementPolicy::DepthPreferred);
+			let mut rng = StdRng::seed_from_u64(mix_seed(self.seed, depth as u64));
+			let mut result = DepthResult { depth, ..DepthResult::default() };
+			for network_first in [true, false] {
+				for _ in 0..self.games_per_side {
+					match play_solver(&mut compiled, &mut solver, network_first, &mut rng) {
+						Some(true) => result.wins += 1,
+						Some(false) => result.losses += 1,
+						None => result.draws += 1
+					}
+				}
+			}
+
+			let weight = (i + 1) as f32;
+			score += weight * result.get_score();
+			total_weight += weight;
+			results.push(result);
+		}
+
+		BenchmarkReport {
+			results,
+			score: if total_weight > 0.0 {score / total_weight} else {0.0}
+		}
+	}
+}
+
+impl FitnessEvaluator for SolverBenchmark {
+	fn evaluate(&mut self, network: &mut Network) -> f32 {
+		self.run(network).score
+	}
+
+	fn evaluate_population(&mut self, population: &mut [Network], num_threads: usize) -> Vec<f32> {
+		let benchmark = &*self;
+		evaluate_in_parallel(population, num_threads, &|network: &mut Network| benchmark.run(network).score)
+	}
+}
+
+// whether the network won, or none for a draw
+fn play_solver<R: Rng>(network: &mut CompiledNetwork, solver: &mut Solver, network_first: bool, rng: &mut R) -> Option<bool> {
+	let mut game = Game::new();
+	let mut sensors = [0.0; NUM_SENSORS];
+	let mut outputs = [0.0; NUM_OUTPUTS];
+	network.reset_state();
+
+	loop {
+		let network_turn = (game.get_turn() == 1) == network_first;
+		let column = if network_turn {
+			coevolution::choose_move(network, &game, &mut sensors, &mut outputs).0
+		}
+		else {
+			solver.negamax_with_rng(&game, rng)
+		};
+
+		if game.play_piece(column) {
+			return Some(network_turn);
+		}
+		if game.check_for_tie() {
+			return None;
+		}
+	}
+}
@@ -0,0 +1,251 @@
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use super::game::Game;
+use super::neat::{CompiledNetwork, FitnessEvaluator, Network};
+
+// one sensor per cell and one output per column
+pub const NUM_SENSORS: usize = 42;
+pub const NUM_OUTPUTS: usize = 7;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+	FirstWin,
+	SecondWin,
+	Draw
+}
+
+pub struct GameRecord {
+	pub outcome: Outcome,
+	pub illegal_preferences: [u32; 2] // times each player's favourite column was full
+}
+
+// the board from the point of view of the player to move: 1 for their pieces, -1 for the opponent's
+pub fn encode_board(game: &Game, sensors: &mut [f32]) {
+	let board = game.get_board();
+	let turn = game.get_turn() as u8;
+	for i in 0..7 {
+		for j in 0..6 {
+			sensors[i * 6 + j] = match board[i][j] {
+				0 => 0.0,
+				x if x == turn => 1.0,
+				_ => -1.0
+			};
+		}
+	}
+}
+
+// the playable column the network prefers, and whether its overall favourite was full
+pub fn choose_move(network: &mut CompiledNetwork, game: &Game, sensors: &mut [f32], outputs: &mut [f32]) -> (usize, bool) {
+	encode_board(game, sensors);
+	network.activate(sensors, outputs);
+
+	let mut favourite = 0;
+	let mut best_move = None;
+	for column in 0..NUM_OUTPUTS {
+		if outputs[column] > outputs[favourite] {
+			favourite = column;
+		}
+		if game.can_play(column) && best_move.is_none_or(|best: usize| outputs[column] > outputs[best]) {
+			best_move = Some(column);
+		}
+	}
+
+	(best_move.unwrap(), !game.can_play(favourite))
+}
+
+pub fn play_game(first: &mut CompiledNetwork, second: &mut CompiledNetwork) -> GameRecord {
+	let mut game = Game::new();
+	let mut sensors = [0.0; NUM_SENSORS];
+	let mut outputs = [0.0; NUM_OUTPUTS];
+	let mut illegal_preferences = [0, 0];
+	first.reset_state();
+	second.reset_state();
+
+	loop {
+		let player = game.get_turn() as usize - 1;
+		let network = if player == 0 {&mut *first} else {&mut *second};
+		let (column, illegal) = choose_move(network, &game, &mut sensors, &mut outputs);
+		if illegal {
+			illegal_preferences[player] += 1;
+		}
+
+		if game.play_piece(column) {
+			let outcome = if player == 0 {Outcome::FirstWin} else {Outcome::SecondWin};
+			return GameRecord { outcome, illegal_preferences };
+		}
+		if game.check_for_tie() {
+			return GameRecord { outcome: Outcome::Draw, illegal_preferences };
+		}
+	}
+}
+
+// scores a network by self-play against the rest of the population and any hall of fame
+// opponents. every pairing is played twice so each network moves first as often as it moves second
+pub struct SelfPlayEvaluator {
+	opponents_per_network: Option<usize>, // round robin against everyone when none
+	illegal_move_penalty: f32,
+	hall_of_fame: Vec<CompiledNetwork>,
+	rng: StdRng
+}
+
+impl SelfPlayEvaluator {
+	pub fn new(opponents_per_network: Option<usize>, illegal_move_penalty: f32, seed: u64) -> SelfPlayEvaluator {
+		SelfPlayEvaluator {
+			opponents_per_network,
+			illegal_move_penalty,
+			hall_of_fame: Vec::new(),
+			rng: StdRng::seed_from_u64(seed)
+		}
+	}
+
+	// hall of fame members are indexed after the population and are always played
+	fn get_pairings(&mut self, population_size: usize) -> Vec<(usize, usize)> {
+		let mut pairings = Vec::new();
+		for i in 0..population_size {
+			for j in population_size..population_size + self.hall_of_fame.len() {
+				pairings.push((i, j));
+				pairings.push((j, i));
+			}
+		}
+		match self.opponents_per_network {
+			Some(opponents) if opponents + 1 < population_size => {
+				for i in 0..population_size {
+					let mut others: Vec<usize> = (0..population_size).filter(|&j| j != i).collect();
+					others.partial_shuffle(&mut self.rng, opponents);
+					for &j in &others[..opponents] {
+						pairings.push((i, j));
+						pairings.push((j, i));
+					}
+				}
+			},
+			_ => {
+				for i in 0..population_size {
+					for j in 0..population_size {
+						if i != j {
+							pairings.push((i, j));
+						}
+					}
+				}
+			}
+		}
+		pairings
+	}
+
+	// 1 for a win and 0.5 for a draw, less the penalty for preferring full columns
+	fn score_games(&self, networks: &mut [CompiledNetwork], pairings: &[(usize, usize)]) -> (Vec<f32>, Vec<u32>) {
+		let mut points = vec![0.0; networks.len()];
+		let mut games = vec![0; networks.len()];
+		for &(first, second) in pairings {
+			let (first_network, second_network) = get_pair(networks, first, second);
+			let record = play_game(first_network, second_network);
+			let (first_points, second_points) = match record.outcome {
+				Outcome::FirstWin => (1.0, 0.0),
+				Outcome::SecondWin => (0.0, 1.0),
+				Outcome::Draw => (0.5, 0.5)
+			};
+			points[first] += first_points - self.illegal_move_penalty * record.illegal_preferences[0] as f32;
+			points[second] += second_points - self.illegal_move_penalty * record.illegal_preferences[1] as f32;
+			games[first] += 1;
+			games[second] += 1;
+		}
+		(points, games)
+	}
+}
+
+impl FitnessEvaluator for SelfPlayEvaluator {
+	// a lone network plays both sides against an opponent choosing columns at random
+	fn evaluate(&mut self, network: &mut Network) -> f32 {
+		let mut compiled = network.compile().expect("network can't be compiled");
+		let mut points = 0.0;
+		for first in [true, false] {
+			let mut game = Game::new();
+			let mut sensors = [0.0; NUM_SENSORS];
+			let mut outputs = [0.0; NUM_OUTPUTS];
+			compiled.reset_state();
+			loop {
+				let network_turn = (game.get_turn() == 1) == first;
+				let column = if network_turn {
+					let (column, illegal) = choose_move(&mut compiled, &game, &mut sensors, &mut outputs);
+					if illegal {
+						points -= self.illegal_move_penalty;
+					}
+					column
+				}
+				else {
+					let moves = game.get_moves();
+					moves[self.rng.gen_range(0, moves.len())].0
+				};
+
+				if game.play_piece(column) {
+					if network_turn {
+						points += 1.0;
+					}
+					break;
+				}
+				if game.check_for_tie() {
+					points += 0.5;
+					break;
+				}
+			}
+		}
+		(points / 2.0).max(0.0)
+	}
+
+	fn evaluate_population(&mut self, population: &mut [Network], num_threads: usize) -> Vec<f32> {
+		let networks: Vec<CompiledNetwork> = population.iter()
+			.map(|network| network.compile().expect("network can't be compiled"))
+			.chain(self.hall_of_fame.iter().cloned())
+			.collect();
+		let pairings = self.get_pairings(population.len());
+
+		let num_threads = num_threads.max(1).min(pairings.len().max(1));
+		let chunk_size = pairings.len().div_ceil(num_threads);
+		let evaluator = &*self;
+		let results: Vec<(Vec<f32>, Vec<u32>)> = std::thread::scope(|scope| {
+			let workers: Vec<_> = pairings.chunks(chunk_size.max(1))
+				.map(|chunk| {
+					let mut networks = networks.clone();
+					scope.spawn(move || evaluator.score_games(&mut networks, chunk))
+				})
+				.collect();
+			workers.into_iter()
+				.map(|worker| worker.join().expect("self-play panicked"))
+				.collect()
+		});
+
+		let mut fitnesses = vec![0.0; population.len()];
+		let mut games = vec![0; population.len()];
+		for (points, played) in results {
+			for i in 0..population.len() {
+				fitnesses[i] += points[i];
+				games[i] += played[i];
+			}
+		}
+		for i in 0..population.len() {
+			fitnesses[i] = if games[i] > 0 {(fitnesses[i] / games[i] as f32).max(0.0)} else {0.0};
+		}
+		fitnesses
+	}
+
+	fn set_hall_of_fame(&mut self, opponents: &[Network]) {
+		self.hall_of_fame = opponents.iter()
+			.map(|network| network.compile().expect("network can't be compiled"))
+			.collect();
+	}
+
+	fn set_seed(&mut self, seed: u64) {
+		self.rng = StdRng::seed_from_u64(seed);
+	}
+}
+
+fn get_pair(networks: &mut [CompiledNetwork], first: usize, second: usize) -> (&mut CompiledNetwork, &mut CompiledNetwork) {
+	if first < second {
+		let (left, right) = networks.split_at_mut(second);
+		(&mut left[first], &mut right[0])
+	}
+	else {
+		let (left, right) = networks.split_at_mut(first);
+		(&mut right[0], &mut left[second])
+	}
+}
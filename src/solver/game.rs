@@ -1,3 +1,10 @@
+// the bottom cell of every column, and every playable cell
+const BOTTOM_MASK: u64 = 0b0000001_0000001_0000001_0000001_0000001_0000001_0000001;
+const BOARD_MASK: u64 = BOTTOM_MASK * 0b111111;
+
+// central columns take part in the most lines, so searching them first prunes the most
+pub const COLUMN_ORDER: [usize; 7] = [3, 2, 4, 1, 5, 0, 6];
+
 pub struct Game {
 	board: u64,
 	mask: u64,
@@ -39,6 +46,15 @@ impl Game {
 		board
 	}
 
+	// unique for every position, since the mask has a free bit at the top of each column
+	pub fn get_key(self: &Game) -> u64 {
+		self.board + self.mask
+	}
+
+	pub fn can_play(self: &Game, column: usize) -> bool {
+		self.mask & (1 << (column * 7 + 5)) == 0
+	}
+
 	pub fn play_piece(self: &mut Game, column: usize) -> bool {
 		let top_mask = 1 << (column * 7 + 5);
 		if self.mask & top_mask == 0 {
@@ -69,13 +85,74 @@ impl Game {
 	}
 
 	pub fn check_for_tie(self: &Game) -> bool {
-		if self.mask == 0b0111111011111101111110111111011111101111110111111 {
+		if self.mask == BOARD_MASK {
 			return true;
 		}
 
 		false
 	}
 
+	pub fn get_move_count(self: &Game) -> usize {
+		Game::pop_count(self.mask) as usize
+	}
+
+	pub fn column_mask(column: usize) -> u64 {
+		0b111111 << (column * 7)
+	}
+
+	// one bit for the next free cell of every column that isn't full
+	pub fn get_possible_moves(self: &Game) -> u64 {
+		(self.mask + BOTTOM_MASK) & BOARD_MASK
+	}
+
+	pub fn can_win_next(self: &Game) -> bool {
+		Game::get_winning_cells(self.board, self.mask) & self.get_possible_moves() != 0
+	}
+
+	// the possible moves that don't let the opponent win straight away. empty when every move loses
+	pub fn get_non_losing_moves(self: &Game) -> u64 {
+		let mut possible = self.get_possible_moves();
+		let opponent_wins = Game::get_winning_cells(self.board ^ self.mask, self.mask);
+		let forced_moves = possible & opponent_wins;
+		if forced_moves != 0 {
+			if forced_moves & (forced_moves - 1) != 0 {
+				return 0; // the opponent has two wins to block
+			}
+			possible = forced_moves;
+		}
+		possible & !(opponent_wins >> 1) // playing under an opponent's winning cell gives it to them
+	}
+
+	// how many winning cells the player to move would have after the move, a cheap measure of threats
+	pub fn get_threat_score(self: &Game, move_bit: u64) -> u32 {
+		Game::pop_count(Game::get_winning_cells(self.board | move_bit, self.mask)) as u32
+	}
+
+	// plays a single bit from get_possible_moves or get_non_losing_moves
+	pub fn play_move(self: &mut Game, move_bit: u64) {
+		self.board ^= self.mask;
+		self.mask |= move_bit;
+		self.first_player = !self.first_player;
+	}
+
+	// empty cells that would complete four in a row for the given pieces
+	fn get_winning_cells(position: u64, mask: u64) -> u64 {
+		// vertical
+		let mut cells = (position << 1) & (position << 2) & (position << 3);
+
+		// horizontal and both diagonals
+		for shift in [7, 6, 8] {
+			let mut pair = (position << shift) & (position << (2 * shift));
+			cells |= pair & (position << (3 * shift));
+			cells |= pair & (position >> shift);
+			pair = (position >> shift) & (position >> (2 * shift));
+			cells |= pair & (position << shift);
+			cells |= pair & (position >> (3 * shift));
+		}
+
+		cells & (BOARD_MASK ^ mask)
+	}
+
 	fn play_piece_no_check(self: &mut Game, column: usize) -> bool {
 		let top_mask = 1 << (column * 7 + 5);
 		if self.mask & top_mask == 0 {
@@ -87,17 +164,6 @@ impl Game {
 		false
 	}
 
-	pub fn get_nodes(self: &Game) -> Vec<Game> {
-		let mut nodes = Vec::new();
-		for i in 0..7 {
-			let mut game = self.copy();
-			if game.play_piece_no_check(i) {
-				nodes.push(game);
-			}
-		}
-		nodes
-	}
-
 	pub fn get_moves(self: &Game) -> Vec<(usize, Game)> {
 		let mut moves = Vec::new();
 		for i in 0..7 {
@@ -135,14 +201,81 @@ impl Game {
 		score as f32
 	}
 
-	fn pop_count(mut x: u64) -> u64 {
-		let m1 = 0x5555555555555555;
-		let m2 = 0x3333333333333333;
-		let m4 = 0x0f0f0f0f0f0f0f0f;
-		let h01 = 0x0101010101010101;
-		x -= (x >> 1) & m1;
-		x = (x & m2) + ((x >> 2) & m2);
-		x = (x + (x >> 4)) & m4;
-		(x * h01) >> 56
+	fn pop_count(x: u64) -> u64 {
+		x.count_ones() as u64
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+	use rand::prelude::*;
+	use rand::rngs::StdRng;
+
+	fn wins_with(game: &Game, column: usize) -> bool {
+		game.can_play(column) && game.copy().play_piece(column)
+	}
+
+	// random moves that never win, or none if the game can only go on by winning
+	pub(crate) fn random_position<R: Rng>(rng: &mut R, moves: usize) -> Option<Game> {
+		let mut game = Game::new();
+		for _ in 0..moves {
+			let columns: Vec<usize> = (0..7).filter(|&c| game.can_play(c) && !wins_with(&game, c)).collect();
+			game.play_piece(*columns.choose(rng)?);
+		}
+		Some(game)
+	}
+
+	#[test]
+	fn pop_count_counts_every_bit() {
+		assert_eq!(Game::pop_count(0), 0);
+		assert_eq!(Game::pop_count(BOARD_MASK), 42);
+		assert_eq!(Game::pop_count(u64::MAX), 64);
+	}
+
+	#[test]
+	fn can_win_next_matches_playing_every_column() {
+		let mut rng = StdRng::seed_from_u64(1);
+		for moves in (0..300).map(|i| 6 + i % 30) {
+			if let Some(game) = random_position(&mut rng, moves) {
+				assert_eq!(game.can_win_next(), (0..7).any(|c| wins_with(&game, c)));
+			}
+		}
+	}
+
+	#[test]
+	fn non_losing_moves_never_let_the_opponent_win() {
+		let mut rng = StdRng::seed_from_u64(2);
+		for moves in (0..300).map(|i| 6 + i % 30) {
+			let game = match random_position(&mut rng, moves) {
+				Some(game) if !game.can_win_next() => game,
+				_ => continue
+			};
+			let non_losing = game.get_non_losing_moves();
+			for column in (0..7).filter(|&c| game.can_play(c)) {
+				let mut next = game.copy();
+				next.play_piece(column);
+				let opponent_wins = (0..7).any(|c| wins_with(&next, c));
+				assert_eq!(non_losing & Game::column_mask(column) != 0, !opponent_wins);
+			}
+		}
+	}
+
+	#[test]
+	fn key_is_unique_to_the_position() {
+		let mut first = Game::new();
+		let mut second = Game::new();
+		for column in [3, 2, 4] {
+			first.play_piece(column);
+		}
+		for column in [4, 2, 3] {
+			second.play_piece(column);
+		}
+		assert_eq!(first.get_key(), second.get_key());
+		second = Game::new();
+		for column in [2, 3, 4] {
+			second.play_piece(column);
+		}
+		assert_ne!(first.get_key(), second.get_key());
 	}
 }
@@ -1,7 +1,37 @@
 use rand::prelude::*;
+use rand::rngs::StdRng;
 use rand::distributions::WeightedIndex;
 use rand_distr::StandardNormal;
 
+pub mod activation;
+pub mod checkpoint;
+pub mod compiled;
+pub mod config;
+mod dot;
+mod encoding;
+pub mod fitness;
+pub mod genome;
+pub mod stats;
+
+pub use activation::Activation;
+pub use compiled::CompiledNetwork;
+pub use config::{ConfigError, HallOfFameSampling, NeatConfig};
+pub use fitness::{FitnessEvaluator, ParallelEvaluator};
+pub use stats::{GenerationStats, LogFormat, Reporter, StatsLogger};
+
+// a seed for one of many independent streams from a single seed. both are hashed with
+// splitmix64, so no other seed and stream pair lands on the same value by accident
+pub(crate) fn mix_seed(seed: u64, stream: u64) -> u64 {
+	splitmix64(splitmix64(seed).wrapping_add(stream))
+}
+
+fn splitmix64(x: u64) -> u64 {
+	let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
+
 pub struct Neat {
 	node_innovation: usize,
 	node_mutations: Vec<(usize, usize)>, // (connection split, innovation number)
@@ -10,26 +40,33 @@ pub struct Neat {
 	num_sensors: usize,
 	num_outputs: usize,
 	population: Vec<Network>,
-	weight_mutation_probability: f64,
-	weight_randomization_probability: f64,
-	weight_mutation_amount: f32,
-	no_crossover_probablility: f64,
-	interspecies_mating_rate: f64,
-	node_mutation_probability: f64,
-	connection_mutation_probability: f64,
-	compatability_constants: (f64, f64, f64),
+	seed: u64,
+	generation: usize,
+	rng: StdRng,
+	config: NeatConfig,
 	minimum_speciation_distance: f64,
-	stagnant_generation_limit: i32,
 	current_best_fitness: f32,
 	generations_since_last_improvement: i32,
+	champion: Option<Network>, // fittest network of the last evaluated generation
+	hall_of_fame: Vec<Network>, // past champions, oldest first
+	species_innovation: usize,
 	species: Vec<Species>,
 	network_groupings: Vec<usize>,
-	network_fitnesses: Vec<f32>
+	network_fitnesses: Vec<f32>,
+	stats: Vec<GenerationStats>,
+	reporters: Vec<Box<dyn Reporter>>
 }
 
 impl Neat {
 	pub fn new(num_sensors: usize, num_outputs: usize) -> Neat {
-		Neat {
+		Neat::with_config(num_sensors, num_outputs, NeatConfig::default()).expect("the default config is valid")
+	}
+
+	// the config is checked here as well as when it is built, since its fields can be set directly
+	pub fn with_config(num_sensors: usize, num_outputs: usize, config: NeatConfig) -> Result<Neat, ConfigError> {
+		config.validate()?;
+		let seed = config.seed.unwrap_or_else(|| thread_rng().gen());
+		Ok(Neat {
 			node_innovation: num_sensors + num_outputs + 1,
 			node_mutations: Vec::new(),
 			connection_list: Vec::new(),
@@ -37,22 +74,22 @@ impl Neat {
 			num_sensors: num_sensors,
 			num_outputs: num_outputs,
 			population: Vec::new(),
-			weight_mutation_probability: 0.8,
-			weight_randomization_probability: 0.1,
-			weight_mutation_amount: 0.2,
-			no_crossover_probablility: 0.25,
-			interspecies_mating_rate: 0.001,
-			node_mutation_probability: 0.03,
-			connection_mutation_probability: 0.05,
-			compatability_constants: (1.0, 1.0, 0.4),
-			minimum_speciation_distance: 3.0,
-			stagnant_generation_limit: 15,
-			current_best_fitness: 0.0,
+			seed,
+			generation: 0,
+			rng: StdRng::seed_from_u64(seed),
+			minimum_speciation_distance: config.minimum_speciation_distance,
+			config,
+			current_best_fitness: f32::NEG_INFINITY,
 			generations_since_last_improvement: 0,
+			champion: None,
+			hall_of_fame: Vec::new(),
+			species_innovation: 0,
 			species: Vec::new(),
 			network_groupings: Vec::new(),
-			network_fitnesses: Vec::new()
-		}
+			network_fitnesses: Vec::new(),
+			stats: Vec::new(),
+			reporters: Vec::new()
+		})
 	}
 
 	pub fn generate_population(&mut self, num_members: usize) {
@@ -67,6 +104,19 @@ impl Neat {
 		&mut self.population[network]
 	}
 
+	pub fn get_champion(&self) -> Option<&Network> {
+		self.champion.as_ref()
+	}
+
+	// stats of every generation evaluated since this Neat was created or loaded
+	pub fn get_stats(&self) -> &[GenerationStats] {
+		&self.stats
+	}
+
+	pub fn add_reporter<R: Reporter + 'static>(&mut self, reporter: R) {
+		self.reporters.push(Box::new(reporter));
+	}
+
 	pub fn find_connection(&self, input_node: usize, output_node: usize) -> Option<usize> {
 		for connection in &self.connection_lookup[input_node] {
 			if self.connection_list[*connection].output == output_node {
@@ -76,19 +126,32 @@ impl Neat {
 		None
 	}
 
-	pub fn calculate_fitnesses(&mut self, fitness_function: fn(&mut Network) -> f32) {
-		self.network_fitnesses = Vec::with_capacity(self.population.len());
-		for individual in &mut self.population {
-			self.network_fitnesses.push(fitness_function(individual));
+	pub fn get_num_threads(&self) -> usize {
+		match self.config.num_threads {
+			0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+			n => n
 		}
 	}
 
+	pub fn calculate_fitnesses<E: FitnessEvaluator + ?Sized>(&mut self, evaluator: &mut E) {
+		let num_threads = self.get_num_threads();
+		let opponents = self.sample_hall_of_fame();
+		evaluator.set_hall_of_fame(&opponents);
+		evaluator.set_seed(self.rng.gen());
+		self.network_fitnesses = evaluator.evaluate_population(&mut self.population, num_threads);
+		assert_eq!(self.network_fitnesses.len(), self.population.len(), "evaluator must give every network a fitness");
+	}
+
 	pub fn group_by_species(&mut self) {
+		for species in &mut self.species {
+			species.individuals.clear();
+		}
+
 		self.network_groupings = Vec::with_capacity(self.population.len());
 		for i in 0..self.population.len() {
 			let mut new_species = true;
 			for j in 0..self.species.len() {
-				if self.minimum_speciation_distance >= self.species[j].get_distance(&mut self.population[i], self.compatability_constants) {
+				if self.minimum_speciation_distance >= self.species[j].get_distance(&mut self.population[i], self.config.compatability_constants) {
 					new_species = false;
 					self.species[j].add_individual(i);
 					self.network_groupings.push(j);
@@ -98,42 +161,275 @@ impl Neat {
 			if new_species {
 				let index = self.species.len();
 				self.network_groupings.push(index);
-				self.species.push(Species::new(&mut self.population[i]));
+				self.species.push(Species::new(self.species_innovation, &mut self.population[i]));
+				self.species_innovation += 1;
 				self.species[index].add_individual(i);
 			}
 		}
+
+		// species no individual matched have died out
+		let mut new_indices = Vec::with_capacity(self.species.len());
+		let mut next_index = 0;
+		for species in &self.species {
+			new_indices.push(next_index);
+			if !species.individuals.is_empty() {
+				next_index += 1;
+			}
+		}
+		self.species.retain(|species| !species.individuals.is_empty());
+		for grouping in &mut self.network_groupings {
+			*grouping = new_indices[*grouping];
+		}
+
+		self.adjust_speciation_distance();
+	}
+
+	fn adjust_speciation_distance(&mut self) {
+		if let Some(target) = self.config.target_species {
+			if self.species.len() < target {
+				self.minimum_speciation_distance -= self.config.speciation_distance_step;
+			}
+			else if self.species.len() > target {
+				self.minimum_speciation_distance += self.config.speciation_distance_step;
+			}
+			let bounds = self.config.speciation_distance_bounds;
+			self.minimum_speciation_distance = self.minimum_speciation_distance.clamp(bounds.0, bounds.1);
+		}
 	}
 
-	pub fn next_generation(&mut self, fitness_function: fn(&mut Network) -> f32) {
-		self.calculate_fitnesses(fitness_function);
+	// plain closures are evaluated one genome at a time on the calling thread;
+	// wrap them in ParallelEvaluator to spread the work over config.num_threads
+	pub fn next_generation<E: FitnessEvaluator + ?Sized>(&mut self, evaluator: &mut E) {
+		self.reseed();
+		self.calculate_fitnesses(evaluator);
 		self.group_by_species();
+		self.update_stagnation();
+		self.update_hall_of_fame();
+		self.report_stats();
 
 		for i in 0..self.population.len() {
 			self.network_fitnesses[i] /= self.species[self.network_groupings[i]].individuals.len() as f32;
 		}
 
+		let reproducing = self.get_reproducing_species();
+		let offspring_quotas = self.get_offspring_quotas(&reproducing);
+		let first_network_dist = WeightedIndex::new(&self.network_fitnesses).ok();
+
 		let mut next_generation = Vec::with_capacity(self.population.len());
-		let mut next_species = Vec::with_capacity(self.species.len());
-		for species in &self.species {
-			if species.individuals.len() > 0 {
-				next_species.push(Species::new(&mut self.population[thread_rng().gen_range(0, species.individuals.len())]));
-				if species.individuals.len() > 4 {
-					let mut fittest_network = species.individuals[0];
-					let mut fittest_fitness = self.network_fitnesses[fittest_network];
-					for i in 1..species.individuals.len() {
-						let net = species.individuals[i];
-						if fittest_fitness < self.network_fitnesses[net] {
-							fittest_network = net;
-							fittest_fitness = self.network_fitnesses[fittest_network];
-						}
+		for (s, &quota) in offspring_quotas.iter().enumerate() {
+			if quota == 0 {
+				continue;
+			}
+			let species_size = self.species[s].individuals.len();
+
+			let mut offspring = quota;
+			if species_size > 4 {
+				let mut fittest_network = self.species[s].individuals[0];
+				let mut fittest_fitness = self.network_fitnesses[fittest_network];
+				for i in 1..species_size {
+					let net = self.species[s].individuals[i];
+					if fittest_fitness < self.network_fitnesses[net] {
+						fittest_network = net;
+						fittest_fitness = self.network_fitnesses[fittest_network];
+					}
+				}
+				next_generation.push(self.population[fittest_network].clone());
+				offspring -= 1;
+			}
+
+			let member_dist = WeightedIndex::new(
+				self.species[s].individuals.iter().map(|&i| self.network_fitnesses[i])
+			).ok();
+			for _ in 0..offspring {
+				let first_parent = Neat::select_parent(&mut self.rng, &self.species[s].individuals, &member_dist);
+				if self.rng.gen_bool(self.config.no_crossover_probablility) {
+					let mut child = self.population[first_parent].clone();
+					child.mutate_network(self);
+					next_generation.push(child);
+					continue;
+				}
+
+				let second_parent = if self.rng.gen_bool(self.config.interspecies_mating_rate) {
+					match &first_network_dist {
+						Some(dist) => dist.sample(&mut self.rng),
+						None => self.rng.gen_range(0, self.population.len())
 					}
-					next_generation.push(self.population[fittest_network].clone());
 				}
+				else {
+					Neat::select_parent(&mut self.rng, &self.species[s].individuals, &member_dist)
+				};
+
+				// the fitter parent passes on its disjoint and excess genes
+				let (fitter, other) = if self.network_fitnesses[second_parent] > self.network_fitnesses[first_parent] {
+					(second_parent, first_parent)
+				}
+				else {
+					(first_parent, second_parent)
+				};
+				let mut fitter_network = self.population[fitter].clone();
+				let mut other_network = self.population[other].clone();
+				next_generation.push(fitter_network.cross(&mut other_network, self));
+			}
+		}
+
+		// surviving species keep their identity and take a representative from this generation's members
+		let mut previous_population = std::mem::replace(&mut self.population, next_generation);
+		let previous_species = std::mem::take(&mut self.species);
+		for (s, mut species) in previous_species.into_iter().enumerate() {
+			if offspring_quotas[s] > 0 {
+				let representative = species.individuals[self.rng.gen_range(0, species.individuals.len())];
+				species.advance_generation(&mut previous_population[representative]);
+				self.species.push(species);
+			}
+		}
+		self.network_groupings.clear();
+		self.generation += 1;
+	}
+
+	// every generation draws from a stream derived from the seed, so a run restored
+	// from a checkpoint continues exactly as the original would have. the initial
+	// population is built from the seed itself, so generations start from stream 1
+	fn reseed(&mut self) {
+		self.rng = StdRng::seed_from_u64(mix_seed(self.seed, self.generation as u64 + 1));
+	}
+
+	pub fn get_seed(&self) -> u64 {
+		self.seed
+	}
+
+	pub fn get_generation(&self) -> usize {
+		self.generation
+	}
+
+	fn update_stagnation(&mut self) {
+		let mut generation_best = f32::NEG_INFINITY;
+		for species in &mut self.species {
+			let mut species_best = f32::NEG_INFINITY;
+			for &individual in &species.individuals {
+				species_best = species_best.max(self.network_fitnesses[individual]);
+			}
+			species.update_best_fitness(species_best);
+			generation_best = generation_best.max(species_best);
+		}
+
+		let mut champion = None;
+		for i in 0..self.population.len() {
+			if champion.is_none_or(|c| self.network_fitnesses[i] > self.network_fitnesses[c]) {
+				champion = Some(i);
+			}
+		}
+		self.champion = champion.map(|c| self.population[c].clone());
+
+		if generation_best > self.current_best_fitness {
+			self.current_best_fitness = generation_best;
+			self.generations_since_last_improvement = 0;
+		}
+		else {
+			self.generations_since_last_improvement += 1;
+		}
+	}
+
+	fn report_stats(&mut self) {
+		let stats = GenerationStats::collect(self);
+		for reporter in &mut self.reporters {
+			reporter.report(&stats);
+		}
+		self.stats.push(stats);
+	}
+
+	fn update_hall_of_fame(&mut self) {
+		if self.config.hall_of_fame_size == 0 {
+			return;
+		}
+		if let Some(champion) = &self.champion {
+			self.hall_of_fame.push(champion.clone());
+		}
+		if self.hall_of_fame.len() > self.config.hall_of_fame_size {
+			let excess = self.hall_of_fame.len() - self.config.hall_of_fame_size;
+			self.hall_of_fame.drain(..excess);
+		}
+	}
+
+	fn sample_hall_of_fame(&mut self) -> Vec<Network> {
+		let samples = self.config.hall_of_fame_samples.min(self.hall_of_fame.len());
+		match self.config.hall_of_fame_sampling {
+			HallOfFameSampling::All => self.hall_of_fame.clone(),
+			HallOfFameSampling::Recent => self.hall_of_fame[self.hall_of_fame.len() - samples..].to_vec(),
+			HallOfFameSampling::Uniform => self.hall_of_fame.choose_multiple(&mut self.rng, samples).cloned().collect()
+		}
+	}
+
+	// stagnant species may not reproduce, except for the best species which is always kept.
+	// if the whole population has stagnated only the top two species get to reproduce
+	fn get_reproducing_species(&mut self) -> Vec<bool> {
+		let mut ranking: Vec<usize> = (0..self.species.len()).collect();
+		ranking.sort_by(|&a, &b| self.species[b].best_fitness.partial_cmp(&self.species[a].best_fitness).unwrap());
+
+		let mut reproducing = vec![false; self.species.len()];
+		if self.generations_since_last_improvement > self.config.population_stagnation_limit {
+			for &s in ranking.iter().take(2) {
+				reproducing[s] = true;
+				self.species[s].generations_since_improvement = 0;
 			}
+			self.generations_since_last_improvement = 0;
+			return reproducing;
 		}
 
-		let first_network_dist = WeightedIndex::new(&self.network_fitnesses).unwrap();
-		//todo
+		for (rank, &s) in ranking.iter().enumerate() {
+			reproducing[s] = rank == 0 || self.species[s].generations_since_improvement < self.config.stagnant_generation_limit;
+		}
+
+		reproducing
+	}
+
+	fn get_offspring_quotas(&self, reproducing: &[bool]) -> Vec<usize> {
+		let population_size = self.population.len();
+		let mut species_fitnesses = Vec::with_capacity(self.species.len());
+		for (s, species) in self.species.iter().enumerate() {
+			let mut acc = 0.0;
+			if reproducing[s] {
+				for &individual in &species.individuals {
+					acc += self.network_fitnesses[individual].max(0.0) as f64;
+				}
+			}
+			species_fitnesses.push(acc);
+		}
+		let total_fitness: f64 = species_fitnesses.iter().sum();
+
+		// fall back to sizing species by membership if there is no usable fitness signal
+		let shares: Vec<f64> = if total_fitness > 0.0 && total_fitness.is_finite() {
+			species_fitnesses
+		}
+		else {
+			(0..self.species.len())
+				.map(|s| if reproducing[s] {self.species[s].individuals.len() as f64} else {0.0})
+				.collect()
+		};
+		let total_share: f64 = shares.iter().sum();
+		let shares: Vec<f64> = shares.iter().map(|share| share / total_share * population_size as f64).collect();
+
+		let mut quotas: Vec<usize> = shares.iter().map(|s| s.floor() as usize).collect();
+		let mut remaining = population_size.saturating_sub(quotas.iter().sum());
+		let mut by_remainder: Vec<usize> = (0..shares.len()).collect();
+		by_remainder.sort_by(|&a, &b| (shares[b] - shares[b].floor()).partial_cmp(&(shares[a] - shares[a].floor())).unwrap());
+		for i in by_remainder {
+			if remaining == 0 {
+				break;
+			}
+			if reproducing[i] {
+				quotas[i] += 1;
+				remaining -= 1;
+			}
+		}
+
+		quotas
+	}
+
+	fn select_parent(rng: &mut StdRng, individuals: &[usize], dist: &Option<WeightedIndex<f32>>) -> usize {
+		match dist {
+			Some(dist) => individuals[dist.sample(rng)],
+			None => individuals[rng.gen_range(0, individuals.len())]
+		}
 	}
 
 	pub fn _get_xor_network() -> Network {
@@ -142,27 +438,27 @@ impl Neat {
 				Node {
 					innovation: 0,
 					node_type: Type::Sensor,
-					activation_value: None
+					activation: Type::Sensor.default_activation()
 				},
 				Node {
 					innovation: 1,
 					node_type: Type::Sensor,
-					activation_value: None
+					activation: Type::Sensor.default_activation()
 				},
 				Node {
 					innovation: 2,
 					node_type: Type::Bias,
-					activation_value: Some(1.0)
+					activation: Type::Bias.default_activation()
 				},
 				Node {
 					innovation: 3,
 					node_type: Type::Output,
-					activation_value: None
+					activation: Type::Output.default_activation()
 				},
 				Node {
 					innovation: 4,
 					node_type: Type::Hidden,
-					activation_value: None
+					activation: Type::Hidden.default_activation()
 				}
 			],
 			connection_genes: vec![
@@ -210,20 +506,44 @@ impl Neat {
 				}
 			],
 			num_sensors: 2,
-			num_outputs: 1
+			num_outputs: 1,
+			recurrent: false
 		}
 	}
 }
 
-struct Species {
+pub struct Species {
+	id: usize,
+	age: i32,
 	genome: Vec<Connection>,
-	pub individuals: Vec<usize>
+	individuals: Vec<usize>,
+	best_fitness: f32,
+	generations_since_improvement: i32
 }
 impl Species {
-	pub fn new(individual: &mut Network) -> Self {
+	pub fn new(id: usize, individual: &mut Network) -> Self {
 		Self {
+			id,
+			age: 0,
 			genome: individual.get_genome(),
-			individuals: Vec::new()
+			individuals: Vec::new(),
+			best_fitness: f32::NEG_INFINITY,
+			generations_since_improvement: 0
+		}
+	}
+
+	pub fn advance_generation(&mut self, representative: &mut Network) {
+		self.genome = representative.get_genome();
+		self.age += 1;
+	}
+
+	pub fn update_best_fitness(&mut self, fitness: f32) {
+		if fitness > self.best_fitness {
+			self.best_fitness = fitness;
+			self.generations_since_improvement = 0;
+		}
+		else {
+			self.generations_since_improvement += 1;
 		}
 	}
 
@@ -285,8 +605,21 @@ pub struct Connection {
 	pub weight: f32,
 	enabled: bool
 }
+impl Connection {
+	pub fn get_input(&self) -> usize {
+		self.input
+	}
 
-#[derive(Copy, Clone)]
+	pub fn get_output(&self) -> usize {
+		self.output
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		self.enabled
+	}
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Type {
 	Sensor,
 	Output,
@@ -294,12 +627,13 @@ pub enum Type {
 	Bias
 }
 impl Type {
-	pub fn activation(self: &Type, input: f32) -> f32 {
+	// the activation each type of node used before activations were configurable
+	pub fn default_activation(self: &Type) -> Activation {
 		match *self {
-			Type::Sensor => input,
-			Type::Output => 1.0 / (1.0 + (-input).exp()),
-			Type::Hidden => input.max(0.0),
-			Type::Bias => 1.0
+			Type::Sensor => Activation::Identity,
+			Type::Output => Activation::Sigmoid,
+			Type::Hidden => Activation::Relu,
+			Type::Bias => Activation::Identity
 		}
 	}
 }
@@ -308,15 +642,15 @@ impl Type {
 pub struct Node {
 	innovation: usize,
 	node_type: Type,
-	activation_value: Option<f32>
+	activation: Activation
 }
 impl Node {
-	pub fn give_input(self: &mut Node, input: f32) {
-		self.activation_value = Some(self.node_type.activation(input));
+	pub fn get_innovation(&self) -> usize {
+		self.innovation
 	}
 
-	pub fn clear(&mut self) {
-		self.activation_value = None;
+	pub fn get_type(&self) -> Type {
+		self.node_type
 	}
 }
 
@@ -325,7 +659,8 @@ pub struct Network {
 	node_genes: Vec<Node>,
 	connection_genes: Vec<Connection>,
 	num_sensors: usize,
-	num_outputs: usize
+	num_outputs: usize,
+	recurrent: bool // whether connections may form loops
 }
 
 impl Network {
@@ -344,7 +679,16 @@ impl Network {
 					else {
 						Type::Output
 					},
-				activation_value: None
+				activation:
+					if (0..global.num_sensors).contains(&i) {
+						global.config.sensor_activation
+					}
+					else if global.num_sensors == i {
+						Type::Bias.default_activation()
+					}
+					else {
+						global.config.output_activation
+					}
 			});
 		}
 
@@ -352,58 +696,37 @@ impl Network {
 			node_genes: nodes,
 			connection_genes: Vec::new(),
 			num_sensors: global.num_sensors,
-			num_outputs: global.num_outputs
+			num_outputs: global.num_outputs,
+			recurrent: global.config.recurrent
 		};
 		network.add_connection(global);
 
 		network
 	}
 
-	pub fn feed_forward(self: &mut Network, inputs: &Vec<f32>) -> Vec<f32> {
-		for i in 0..self.num_sensors {
-			self.node_genes[i].give_input(inputs[i]);
-		}
-		self.node_genes[self.num_sensors].give_input(1.0);
-
-		let mut output = Vec::with_capacity(self.num_outputs);
-		for output_node in self.num_sensors + 1..self.num_outputs + self.num_sensors + 1 {
-			output.push(self.evaluate_node(output_node));
-		}
-		for node in &mut self.node_genes {
-			node.clear();
-		}
-		
-		output
+	pub fn get_nodes(&self) -> &[Node] {
+		&self.node_genes
 	}
 
-	fn evaluate_node(self: &mut Network, node: usize) -> f32 {
-		let mut acc = 0.0;
-		for i in 0..self.connection_genes.len() {
-			if self.connection_genes[i].enabled && self.connection_genes[i].output == node {
-				let prev_node = self.connection_genes[i].input;
-				acc += self.connection_genes[i].weight *
-					self.node_genes[prev_node].activation_value
-					.unwrap_or_else(|| self.evaluate_node(prev_node));
-			}
-		}
-
-		self.node_genes[node].give_input(acc);
-
-		self.node_genes[node].activation_value.unwrap()
+	pub fn get_connections(&self) -> &[Connection] {
+		&self.connection_genes
 	}
 
 	pub fn add_connection(self: &mut Network, global: &mut Neat) {
-		let to_node = rand::thread_rng().gen_range(self.num_sensors + 1, self.node_genes.len());
+		let to_node = self.node_genes[global.rng.gen_range(self.num_sensors + 1, self.node_genes.len())].innovation;
 
-		let mut possible_from_nodes = vec![true; global.node_innovation];
-
-		for i in self.num_sensors + 1..self.num_outputs + self.num_sensors + 1 { // connection can't come from output node
-			possible_from_nodes[i] = false;
+		let mut possible_from_nodes = vec![false; global.node_innovation];
+		for node in &self.node_genes {
+			if self.recurrent || !matches!(node.node_type, Type::Output) { // connection can't come from output node
+				possible_from_nodes[node.innovation] = true;
+			}
 		}
 
-		let mut nodes_to_search = vec![to_node];
+		let mut searched = vec![false; global.node_innovation];
+		let mut nodes_to_search = if self.recurrent {Vec::new()} else {vec![to_node]}; // loops are only allowed in recurrent networks
 		while let Some(current_node) = nodes_to_search.pop() {
-			if possible_from_nodes[current_node] { // node hasn't been searched
+			if !searched[current_node] {
+				searched[current_node] = true;
 				possible_from_nodes[current_node] = false;
 
 				for connection_ptr in &global.connection_lookup[current_node] {
@@ -419,12 +742,15 @@ impl Network {
 			}
 		}
 
-		let from_node = from_nodes[rand::thread_rng().gen_range(0, from_nodes.len())];
+		if from_nodes.is_empty() {
+			return;
+		}
+		let from_node = from_nodes[global.rng.gen_range(0, from_nodes.len())];
 
 		if let Some(innovation_num) = global.find_connection(from_node, to_node) {
 			for connection in &mut self.connection_genes {
 				if connection.innovation == innovation_num {
-					connection.weight = thread_rng().sample(StandardNormal);
+					connection.weight = global.rng.sample(StandardNormal);
 					connection.enabled = true;
 					return;
 				}
@@ -433,7 +759,7 @@ impl Network {
 				innovation: innovation_num,
 				input: from_node,
 				output: to_node,
-				weight: thread_rng().sample(StandardNormal),
+				weight: global.rng.sample(StandardNormal),
 				enabled: true
 			});
 			return;
@@ -442,7 +768,7 @@ impl Network {
 			innovation: global.connection_list.len(),
 			input: from_node,
 			output: to_node,
-			weight: thread_rng().sample(StandardNormal),
+			weight: global.rng.sample(StandardNormal),
 			enabled: true
 		});
 		global.connection_lookup[from_node].push(global.connection_list.len());
@@ -462,13 +788,22 @@ impl Network {
 				enabled_connections.push(i);
 			}
 		}
-		let connection_to_split = enabled_connections[rand::thread_rng().gen_range(0, enabled_connections.len())];
-		self.connection_genes[connection_to_split].enabled = false;
+		if enabled_connections.is_empty() {
+			return;
+		}
+		let connection_to_split = enabled_connections[global.rng.gen_range(0, enabled_connections.len())];
+		let split_innovation = self.connection_genes[connection_to_split].innovation;
 		let input_node = self.connection_genes[connection_to_split].input;
 		let output_node = self.connection_genes[connection_to_split].output;
 		let connection_weight = self.connection_genes[connection_to_split].weight;
 
-		let previous_mutation = global.node_mutations.iter().find(|&&i| i.0 == connection_to_split);
+		let previous_mutation = global.node_mutations.iter().find(|&&i| i.0 == split_innovation).copied();
+		if let Some(x) = previous_mutation {
+			if self.node_genes.iter().any(|node| node.innovation == x.1) { // this connection was already split here
+				return;
+			}
+		}
+		self.connection_genes[connection_to_split].enabled = false;
 		let connection_innovation = match previous_mutation {
 			Some(x) => global.find_connection(input_node, x.1),
 			None => None
@@ -481,7 +816,7 @@ impl Network {
 		let new_node = Node {
 			innovation: node_innovation,
 			node_type: Type::Hidden,
-			activation_value: None
+			activation: global.config.hidden_activation
 		};
 		let connection_to = Connection {
 			innovation: connection_innovation,
@@ -498,13 +833,13 @@ impl Network {
 			enabled: true
 		};
 
-		if None == previous_mutation {
+		if previous_mutation.is_none() {
 			global.node_innovation += 1;
 			global.connection_list.push(connection_to);
 			global.connection_list.push(connection_from);
 			global.connection_lookup[input_node].push(connection_innovation);
 			global.connection_lookup.push(vec![connection_innovation + 1]);
-			global.node_mutations.push((connection_to_split, node_innovation));
+			global.node_mutations.push((split_innovation, node_innovation));
 		}
 
 		self.node_genes.push(new_node);
@@ -519,30 +854,37 @@ impl Network {
 	}
 
 	pub fn cross(&mut self, other: &mut Self, global: &mut Neat) -> Self {
-		let mut crossed_connections = self.connection_genes.clone();
+		let mut crossed_connections = self.get_genome();
+		let other_genome = other.get_genome();
 		let mut i = 0;
 		for connection in &mut crossed_connections {
-			match loop {
+			while i < other_genome.len() && other_genome[i].innovation < connection.innovation {
 				i += 1;
-				if i >= other.connection_genes.len() {
-					break None;
-				}
-				if connection.innovation == other.connection_genes[i].innovation {
-					break Some(i);
+			}
+			if i >= other_genome.len() {
+				break;
+			}
+			if other_genome[i].innovation == connection.innovation && global.rng.gen_bool(0.5) {
+				connection.weight = other_genome[i].weight;
+			}
+		}
+
+		// nodes both parents share take their activation from either parent
+		let mut crossed_nodes = self.node_genes.clone();
+		for node in &mut crossed_nodes {
+			if let Some(other_node) = other.node_genes.iter().find(|x| x.innovation == node.innovation) {
+				if global.rng.gen_bool(0.5) {
+					node.activation = other_node.activation;
 				}
-			} {
-				Some(x) => if rand::thread_rng().gen_bool(0.5) {
-					connection.weight = other.connection_genes[x].weight;
-				},
-				None => break
-			};
+			}
 		}
 
 		let mut new_network = Network {
-			node_genes: self.node_genes.clone(),
+			node_genes: crossed_nodes,
 			connection_genes: crossed_connections,
 			num_sensors: self.num_sensors,
-			num_outputs: self.num_outputs
+			num_outputs: self.num_outputs,
+			recurrent: self.recurrent
 		};
 
 		new_network.mutate_network(global);
@@ -551,21 +893,85 @@ impl Network {
 	}
 
 	pub fn mutate_network(&mut self, global: &mut Neat) {
-		if rand::thread_rng().gen_bool(global.weight_mutation_probability) {
-			let connection_to_mutate = rand::thread_rng().gen_range(0, self.connection_genes.len());
-			if rand::thread_rng().gen_bool(global.weight_randomization_probability) {
-				self.connection_genes[connection_to_mutate].weight = thread_rng().sample(StandardNormal);
+		if global.rng.gen_bool(global.config.weight_mutation_probability) {
+			let connection_to_mutate = global.rng.gen_range(0, self.connection_genes.len());
+			if global.rng.gen_bool(global.config.weight_randomization_probability) {
+				self.connection_genes[connection_to_mutate].weight = global.rng.sample(StandardNormal);
 			}
 			else {
-				self.connection_genes[connection_to_mutate].weight += thread_rng().sample::<f32, _>(StandardNormal) * global.weight_mutation_amount;
+				self.connection_genes[connection_to_mutate].weight += global.rng.sample::<f32, _>(StandardNormal) * global.config.weight_mutation_amount;
 			}
 		}
 
-		if rand::thread_rng().gen_bool(global.node_mutation_probability) {
+		if global.rng.gen_bool(global.config.node_mutation_probability) {
 			self.add_node(global);
 		}
-		if rand::thread_rng().gen_bool(global.connection_mutation_probability) {
+		if global.rng.gen_bool(global.config.connection_mutation_probability) {
 			self.add_connection(global);
 		}
+		if global.rng.gen_bool(global.config.activation_mutation_probability) {
+			self.mutate_activation(global);
+		}
+	}
+
+	pub fn mutate_activation(&mut self, global: &mut Neat) {
+		let first_hidden = self.num_sensors + self.num_outputs + 1;
+		if self.node_genes.len() == first_hidden || global.config.activation_options.is_empty() {
+			return;
+		}
+		let node = global.rng.gen_range(first_hidden, self.node_genes.len());
+		let options = &global.config.activation_options;
+		self.node_genes[node].activation = options[global.rng.gen_range(0, options.len())];
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	pub(crate) fn xor_fitness(network: &mut Network) -> f32 {
+		let mut compiled = network.compile().unwrap();
+		let mut output = [0.0];
+		let mut fitness = 0.0;
+		for (inputs, target) in [([0.0, 0.0], 0.0), ([0.0, 1.0], 1.0), ([1.0, 0.0], 1.0), ([1.0, 1.0], 0.0)] {
+			compiled.activate(&inputs, &mut output);
+			fitness += 1.0 - (target - output[0]) * (target - output[0]);
+		}
+		fitness
+	}
+
+	pub(crate) fn seeded_neat(seed: u64) -> Neat {
+		let config = NeatConfig::builder()
+			.seed(Some(seed))
+			.target_species(Some(5))
+			.num_threads(2)
+			.build()
+			.unwrap();
+		let mut neat = Neat::with_config(2, 1, config).unwrap();
+		neat.generate_population(50);
+		neat
+	}
+
+	#[test]
+	fn same_seed_gives_same_run() {
+		let mut first = seeded_neat(7);
+		let mut second = seeded_neat(7);
+		for _ in 0..10 {
+			first.next_generation(&mut ParallelEvaluator(xor_fitness));
+			second.next_generation(&mut ParallelEvaluator(xor_fitness));
+		}
+		assert_eq!(first.get_stats(), second.get_stats());
+		assert_eq!(first.to_checkpoint_bytes(), second.to_checkpoint_bytes());
+	}
+
+	#[test]
+	fn different_seeds_give_different_runs() {
+		let mut first = seeded_neat(7);
+		let mut second = seeded_neat(8);
+		for _ in 0..3 {
+			first.next_generation(&mut ParallelEvaluator(xor_fitness));
+			second.next_generation(&mut ParallelEvaluator(xor_fitness));
+		}
+		assert_ne!(first.to_checkpoint_bytes(), second.to_checkpoint_bytes());
 	}
 }
@@ -0,0 +1,82 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Activation {
+	Sigmoid,
+	SteepenedSigmoid,
+	Tanh,
+	Relu,
+	Gaussian,
+	Sine,
+	Abs,
+	Step,
+	Identity
+}
+
+impl Activation {
+	pub const ALL: [Activation; 9] = [
+		Activation::Sigmoid,
+		Activation::SteepenedSigmoid,
+		Activation::Tanh,
+		Activation::Relu,
+		Activation::Gaussian,
+		Activation::Sine,
+		Activation::Abs,
+		Activation::Step,
+		Activation::Identity
+	];
+
+	pub fn apply(self, input: f32) -> f32 {
+		match self {
+			Activation::Sigmoid => 1.0 / (1.0 + (-input).exp()),
+			Activation::SteepenedSigmoid => 1.0 / (1.0 + (-4.9 * input).exp()), // as used in the original NEAT paper
+			Activation::Tanh => input.tanh(),
+			Activation::Relu => input.max(0.0),
+			Activation::Gaussian => (-input * input).exp(),
+			Activation::Sine => input.sin(),
+			Activation::Abs => input.abs(),
+			Activation::Step => if input > 0.0 {1.0} else {0.0},
+			Activation::Identity => input
+		}
+	}
+
+	pub fn get_name(self) -> &'static str {
+		match self {
+			Activation::Sigmoid => "sigmoid",
+			Activation::SteepenedSigmoid => "steepened_sigmoid",
+			Activation::Tanh => "tanh",
+			Activation::Relu => "relu",
+			Activation::Gaussian => "gaussian",
+			Activation::Sine => "sine",
+			Activation::Abs => "abs",
+			Activation::Step => "step",
+			Activation::Identity => "identity"
+		}
+	}
+
+	pub(crate) fn to_id(self) -> u8 {
+		Activation::ALL.iter().position(|&a| a == self).unwrap() as u8
+	}
+
+	pub(crate) fn from_id(id: u8) -> Option<Activation> {
+		Activation::ALL.get(id as usize).copied()
+	}
+}
+
+impl fmt::Display for Activation {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.get_name())
+	}
+}
+
+impl FromStr for Activation {
+	type Err = String;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Activation::ALL.iter()
+			.find(|a| a.get_name() == name)
+			.copied()
+			.ok_or_else(|| format!("unknown activation function {}", name))
+	}
+}
@@ -0,0 +1,329 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use super::config::ConfigError;
+use super::encoding::{ByteReader, ByteWriter};
+use super::{Neat, Species};
+
+const MAGIC: &[u8; 8] = b"NEATCKPT";
+const VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+	Io(std::io::Error),
+	NotACheckpoint,
+	UnsupportedVersion(u32),
+	Corrupt(String),
+	Config(ConfigError)
+}
+
+impl fmt::Display for CheckpointError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			CheckpointError::Io(error) => write!(f, "could not access checkpoint: {}", error),
+			CheckpointError::NotACheckpoint => write!(f, "file is not a NEAT checkpoint"),
+			CheckpointError::UnsupportedVersion(version) => write!(f, "unsupported checkpoint version {}", version),
+			CheckpointError::Corrupt(message) => write!(f, "corrupt checkpoint: {}", message),
+			CheckpointError::Config(error) => write!(f, "checkpoint has a bad config: {}", error)
+		}
+	}
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<std::io::Error> for CheckpointError {
+	fn from(error: std::io::Error) -> Self {
+		CheckpointError::Io(error)
+	}
+}
+
+impl From<String> for CheckpointError {
+	fn from(message: String) -> Self {
+		CheckpointError::Corrupt(message)
+	}
+}
+
+impl Neat {
+	pub fn save_checkpoint<P: AsRef<Path>>(&self, path: P) -> Result<(), CheckpointError> {
+		fs::write(path, self.to_checkpoint_bytes())?;
+		Ok(())
+	}
+
+	pub fn load_checkpoint<P: AsRef<Path>>(path: P) -> Result<Neat, CheckpointError> {
+		Neat::from_checkpoint_bytes(&fs::read(path)?)
+	}
+
+	pub fn to_checkpoint_bytes(&self) -> Vec<u8> {
+		let mut writer = ByteWriter::new();
+		writer.write_bytes(MAGIC);
+		writer.write_u32(VERSION);
+
+		writer.write_str(&self.config.to_string());
+		writer.write_usize(self.num_sensors);
+		writer.write_usize(self.num_outputs);
+		writer.write_u64(self.seed);
+		writer.write_usize(self.generation);
+
+		writer.write_usize(self.node_innovation);
+		writer.write_usize(self.node_mutations.len());
+		for mutation in &self.node_mutations {
+			writer.write_usize(mutation.0);
+			writer.write_usize(mutation.1);
+		}
+		writer.write_connections(&self.connection_list);
+		writer.write_usize(self.connection_lookup.len());
+		for connections in &self.connection_lookup {
+			writer.write_usize(connections.len());
+			for &connection in connections {
+				writer.write_usize(connection);
+			}
+		}
+
+		writer.write_usize(self.population.len());
+		for network in &self.population {
+			writer.write_network(network);
+		}
+
+		writer.write_f64(self.minimum_speciation_distance);
+		writer.write_f32(self.current_best_fitness);
+		writer.write_i32(self.generations_since_last_improvement);
+		match &self.champion {
+			Some(champion) => {
+				writer.write_bool(true);
+				writer.write_network(champion);
+			},
+			None => writer.write_bool(false)
+		}
+		writer.write_usize(self.hall_of_fame.len());
+		for network in &self.hall_of_fame {
+			writer.write_network(network);
+		}
+		writer.write_usize(self.species_innovation);
+		writer.write_usize(self.species.len());
+		for species in &self.species {
+			writer.write_usize(species.id);
+			writer.write_i32(species.age);
+			writer.write_connections(&species.genome);
+			writer.write_usize(species.individuals.len());
+			for &individual in &species.individuals {
+				writer.write_usize(individual);
+			}
+			writer.write_f32(species.best_fitness);
+			writer.write_i32(species.generations_since_improvement);
+		}
+
+		writer.write_usize(self.network_groupings.len());
+		for &grouping in &self.network_groupings {
+			writer.write_usize(grouping);
+		}
+		writer.write_usize(self.network_fitnesses.len());
+		for &fitness in &self.network_fitnesses {
+			writer.write_f32(fitness);
+		}
+
+		writer.into_bytes()
+	}
+
+	pub fn from_checkpoint_bytes(bytes: &[u8]) -> Result<Neat, CheckpointError> {
+		let mut reader = ByteReader::new(bytes);
+		if reader.read_bytes(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+			return Err(CheckpointError::NotACheckpoint);
+		}
+		let version = reader.read_u32()?;
+		if version != VERSION {
+			return Err(CheckpointError::UnsupportedVersion(version));
+		}
+
+		let config = reader.read_str()?.parse().map_err(CheckpointError::Config)?;
+		let num_sensors = reader.read_usize()?;
+		let num_outputs = reader.read_usize()?;
+		let mut neat = Neat::with_config(num_sensors, num_outputs, config).map_err(CheckpointError::Config)?;
+		neat.seed = reader.read_u64()?;
+		neat.generation = reader.read_usize()?;
+		neat.reseed();
+
+		neat.node_innovation = reader.read_usize()?;
+		let num_mutations = reader.read_length(16)?;
+		neat.node_mutations = Vec::with_capacity(num_mutations);
+		for _ in 0..num_mutations {
+			neat.node_mutations.push((reader.read_usize()?, reader.read_usize()?));
+		}
+		neat.connection_list = reader.read_connections()?;
+		let num_lookups = reader.read_length(8)?;
+		neat.connection_lookup = Vec::with_capacity(num_lookups);
+		for _ in 0..num_lookups {
+			let num_connections = reader.read_length(8)?;
+			let mut connections = Vec::with_capacity(num_connections);
+			for _ in 0..num_connections {
+				connections.push(reader.read_usize()?);
+			}
+			neat.connection_lookup.push(connections);
+		}
+
+		let population_size = reader.read_length(24)?;
+		for _ in 0..population_size {
+			neat.population.push(reader.read_network()?);
+		}
+
+		neat.minimum_speciation_distance = reader.read_f64()?;
+		neat.current_best_fitness = reader.read_f32()?;
+		neat.generations_since_last_improvement = reader.read_i32()?;
+		if reader.read_bool()? {
+			neat.champion = Some(reader.read_network()?);
+		}
+		let hall_of_fame_size = reader.read_length(24)?;
+		for _ in 0..hall_of_fame_size {
+			neat.hall_of_fame.push(reader.read_network()?);
+		}
+		neat.species_innovation = reader.read_usize()?;
+		let num_species = reader.read_length(32)?;
+		for _ in 0..num_species {
+			let id = reader.read_usize()?;
+			let age = reader.read_i32()?;
+			let genome = reader.read_connections()?;
+			let num_individuals = reader.read_length(8)?;
+			let mut individuals = Vec::with_capacity(num_individuals);
+			for _ in 0..num_individuals {
+				individuals.push(reader.read_usize()?);
+			}
+			neat.species.push(Species {
+				id,
+				age,
+				genome,
+				individuals,
+				best_fitness: reader.read_f32()?,
+				generations_since_improvement: reader.read_i32()?
+			});
+		}
+
+		let num_groupings = reader.read_length(8)?;
+		for _ in 0..num_groupings {
+			neat.network_groupings.push(reader.read_usize()?);
+		}
+		let num_fitnesses = reader.read_length(4)?;
+		for _ in 0..num_fitnesses {
+			neat.network_fitnesses.push(reader.read_f32()?);
+		}
+
+		if !reader.is_finished() {
+			return Err(CheckpointError::Corrupt("trailing data after checkpoint".to_string()));
+		}
+		neat.check_consistency()?;
+
+		Ok(neat)
+	}
+
+	fn check_consistency(&self) -> Result<(), CheckpointError> {
+		if self.connection_lookup.len() != self.node_innovation {
+			return Err(CheckpointError::Corrupt("connection lookup does not cover every node".to_string()));
+		}
+		for connection in &self.connection_list {
+			if connection.input >= self.node_innovation || connection.output >= self.node_innovation {
+				return Err(CheckpointError::Corrupt(format!("connection innovation {} joins unknown nodes", connection.innovation)));
+			}
+		}
+		if self.connection_lookup.iter().flatten().any(|&c| c >= self.connection_list.len()) {
+			return Err(CheckpointError::Corrupt("connection lookup refers to an unknown connection".to_string()));
+		}
+		if self.node_mutations.iter().any(|&(split, node)| split >= self.connection_list.len() || node >= self.node_innovation) {
+			return Err(CheckpointError::Corrupt("node mutation refers to an unknown connection or node".to_string()));
+		}
+
+		// networks are checked in full so a bad checkpoint fails here rather than panicking mid run
+		for network in self.population.iter().chain(&self.champion).chain(&self.hall_of_fame) {
+			if network.num_sensors != self.num_sensors || network.num_outputs != self.num_outputs {
+				return Err(CheckpointError::Corrupt("network has the wrong number of sensors or outputs".to_string()));
+			}
+			network.validate().map_err(|message| CheckpointError::Corrupt(format!("invalid network: {}", message)))?;
+			for node in &network.node_genes {
+				if node.innovation >= self.node_innovation {
+					return Err(CheckpointError::Corrupt(format!("unknown node innovation {}", node.innovation)));
+				}
+			}
+			for connection in &network.connection_genes {
+				if connection.innovation >= self.connection_list.len() {
+					return Err(CheckpointError::Corrupt(format!("unknown connection innovation {}", connection.innovation)));
+				}
+			}
+		}
+		for species in &self.species {
+			if species.genome.is_empty() {
+				return Err(CheckpointError::Corrupt(format!("species {} has an empty genome", species.id)));
+			}
+			if species.individuals.iter().any(|&i| i >= self.population.len()) {
+				return Err(CheckpointError::Corrupt(format!("species {} refers to a missing individual", species.id)));
+			}
+		}
+		if self.network_groupings.iter().any(|&g| g >= self.species.len()) {
+			return Err(CheckpointError::Corrupt("network grouped into a missing species".to_string()));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::solver::neat::tests::{seeded_neat, xor_fitness};
+	use crate::solver::neat::{NeatConfig, ParallelEvaluator};
+	use crate::solver::coevolution::{SelfPlayEvaluator, NUM_OUTPUTS, NUM_SENSORS};
+
+	#[test]
+	fn resumed_run_continues_identically() {
+		let mut original = seeded_neat(11);
+		for _ in 0..5 {
+			original.next_generation(&mut ParallelEvaluator(xor_fitness));
+		}
+		let bytes = original.to_checkpoint_bytes();
+		let mut resumed = Neat::from_checkpoint_bytes(&bytes).unwrap();
+		assert_eq!(resumed.to_checkpoint_bytes(), bytes);
+
+		for _ in 0..5 {
+			original.next_generation(&mut ParallelEvaluator(xor_fitness));
+			resumed.next_generation(&mut ParallelEvaluator(xor_fitness));
+		}
+		assert_eq!(&original.get_stats()[5..], resumed.get_stats());
+		assert_eq!(original.to_checkpoint_bytes(), resumed.to_checkpoint_bytes());
+	}
+
+	// self-play picks opponents at random, so this checks the evaluator is seeded from the run
+	#[test]
+	fn resumed_self_play_run_continues_identically() {
+		let config = NeatConfig::builder()
+			.seed(Some(13))
+			.hall_of_fame_size(4)
+			.hall_of_fame_samples(2)
+			.num_threads(2)
+			.build()
+			.unwrap();
+		let mut original = Neat::with_config(NUM_SENSORS, NUM_OUTPUTS, config).unwrap();
+		original.generate_population(20);
+		let mut original_evaluator = SelfPlayEvaluator::new(Some(3), 0.05, 1);
+		for _ in 0..4 {
+			original.next_generation(&mut original_evaluator);
+		}
+
+		let mut resumed = Neat::from_checkpoint_bytes(&original.to_checkpoint_bytes()).unwrap();
+		let mut resumed_evaluator = SelfPlayEvaluator::new(Some(3), 0.05, 2);
+		for _ in 0..4 {
+			original.next_generation(&mut original_evaluator);
+			resumed.next_generation(&mut resumed_evaluator);
+		}
+		assert_eq!(&original.get_stats()[4..], resumed.get_stats());
+		assert_eq!(original.to_checkpoint_bytes(), resumed.to_checkpoint_bytes());
+	}
+
+	#[test]
+	fn truncated_checkpoint_is_rejected() {
+		let mut neat = seeded_neat(11);
+		neat.next_generation(&mut ParallelEvaluator(xor_fitness));
+		let bytes = neat.to_checkpoint_bytes();
+		assert!(Neat::from_checkpoint_bytes(&bytes[..bytes.len() - 1]).is_err());
+		assert!(matches!(Neat::from_checkpoint_bytes(b"not a checkpoint"), Err(CheckpointError::NotACheckpoint)));
+
+		let mut newer = bytes.clone();
+		newer[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+		assert!(matches!(Neat::from_checkpoint_bytes(&newer), Err(CheckpointError::UnsupportedVersion(_))));
+	}
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use super::{Activation, Network};
+
+// a network flattened into an evaluation plan: nodes that feed the outputs in topological
+// order, each with a contiguous run of (source slot, weight) pairs to sum over.
+// in recurrent networks a connection looping back reads its source's value from the previous step
+#[derive(Clone)]
+pub struct CompiledNetwork {
+	num_sensors: usize,
+	num_outputs: usize,
+	order: Vec<usize>,
+	activations: Vec<Activation>,
+	incoming_start: Vec<usize>,
+	incoming: Vec<(usize, f32)>,
+	values: Vec<f32>
+}
+
+impl CompiledNetwork {
+	// node values from the last step, in the same order as the network's nodes.
+	// nodes that don't lead to an output are never evaluated and stay at 0
+	pub fn get_values(&self) -> &[f32] {
+		&self.values
+	}
+
+	// clears the node values carried between steps of a recurrent network
+	pub fn reset_state(&mut self) {
+		for value in &mut self.values {
+			*value = 0.0;
+		}
+	}
+
+	// advances the network one time step
+	pub fn activate(&mut self, inputs: &[f32], outputs: &mut [f32]) {
+		assert_eq!(inputs.len(), self.num_sensors, "wrong number of inputs");
+		assert_eq!(outputs.len(), self.num_outputs, "wrong number of outputs");
+
+		for (i, &input) in inputs.iter().enumerate() {
+			self.values[i] = self.activations[i].apply(input);
+		}
+		self.values[self.num_sensors] = 1.0;
+
+		for (k, &slot) in self.order.iter().enumerate() {
+			let mut acc = 0.0;
+			for &(source, weight) in &self.incoming[self.incoming_start[k]..self.incoming_start[k + 1]] {
+				acc += weight * self.values[source];
+			}
+			self.values[slot] = self.activations[slot].apply(acc);
+		}
+
+		outputs.copy_from_slice(&self.values[self.num_sensors + 1..self.num_sensors + 1 + self.num_outputs]);
+	}
+}
+
+impl Network {
+	// fails if the enabled connections of a non-recurrent network contain a cycle
+	pub fn compile(&self) -> Result<CompiledNetwork, String> {
+		let num_nodes = self.node_genes.len();
+		let mut positions = HashMap::with_capacity(num_nodes);
+		for (i, node) in self.node_genes.iter().enumerate() {
+			positions.insert(node.innovation, i);
+		}
+
+		let mut incoming_lists = vec![Vec::new(); num_nodes];
+		for connection in &self.connection_genes {
+			if connection.enabled {
+				incoming_lists[positions[&connection.output]].push((positions[&connection.input], connection.weight));
+			}
+		}
+
+		// only nodes the outputs depend on need evaluating
+		let mut needed = vec![false; num_nodes];
+		let mut nodes_to_search: Vec<usize> = (self.num_sensors + 1..self.num_sensors + 1 + self.num_outputs).collect();
+		while let Some(node) = nodes_to_search.pop() {
+			if !needed[node] {
+				needed[node] = true;
+				for &(source, _) in &incoming_lists[node] {
+					nodes_to_search.push(source);
+				}
+			}
+		}
+
+		// post order depth first search gives every node after all of its sources
+		let mut order = Vec::with_capacity(num_nodes);
+		let mut state = vec![0u8; num_nodes]; // 0 unvisited, 1 on the stack, 2 finished
+		for start in self.num_sensors + 1..num_nodes {
+			if !needed[start] || state[start] != 0 {
+				continue;
+			}
+			let mut stack = vec![(start, 0)];
+			state[start] = 1;
+			while let Some((node, next)) = stack.pop() {
+				if next < incoming_lists[node].len() {
+					stack.push((node, next + 1));
+					let source = incoming_lists[node][next].0;
+					match state[source] {
+						0 if source > self.num_sensors => {
+							state[source] = 1;
+							stack.push((source, 0));
+						},
+						1 if self.recurrent => {}, // evaluated later, so still holds its previous value
+						1 => return Err(format!("network has a cycle through node {}", self.node_genes[source].innovation)),
+						_ => {}
+					}
+				}
+				else {
+					state[node] = 2;
+					order.push(node);
+				}
+			}
+		}
+
+		let mut incoming_start = Vec::with_capacity(order.len() + 1);
+		let mut incoming = Vec::new();
+		for &node in &order {
+			incoming_start.push(incoming.len());
+			incoming.extend_from_slice(&incoming_lists[node]);
+		}
+		incoming_start.push(incoming.len());
+
+		Ok(CompiledNetwork {
+			num_sensors: self.num_sensors,
+			num_outputs: self.num_outputs,
+			order,
+			activations: self.node_genes.iter().map(|node| node.activation).collect(),
+			incoming_start,
+			incoming,
+			values: vec![0.0; num_nodes]
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::solver::neat::tests::xor_fitness;
+	use crate::solver::neat::{Connection, Neat, NeatConfig, Node, ParallelEvaluator, Type};
+	use rand::prelude::*;
+	use rand::rngs::StdRng;
+
+	// evaluates each node by recursing through its incoming connections, which is slow but obviously right
+	fn reference_outputs(network: &Network, inputs: &[f32]) -> Vec<f32> {
+		let mut values = HashMap::new();
+		for (i, &input) in inputs.iter().enumerate() {
+			values.insert(i, network.node_genes[i].activation.apply(input));
+		}
+		values.insert(network.num_sensors, 1.0);
+		(network.num_sensors + 1..network.num_sensors + 1 + network.num_outputs)
+			.map(|output| reference_value(network, output, &mut values))
+			.collect()
+	}
+
+	fn reference_value(network: &Network, innovation: usize, values: &mut HashMap<usize, f32>) -> f32 {
+		if let Some(&value) = values.get(&innovation) {
+			return value;
+		}
+		let mut acc = 0.0;
+		for connection in network.connection_genes.iter().filter(|c| c.enabled && c.output == innovation) {
+			acc += connection.weight * reference_value(network, connection.input, values);
+		}
+		let node = network.node_genes.iter().find(|node| node.innovation == innovation).unwrap();
+		let value = node.activation.apply(acc);
+		values.insert(innovation, value);
+		value
+	}
+
+	fn assert_matches_reference(network: &Network, rng: &mut StdRng) {
+		let mut compiled = network.compile().unwrap();
+		let mut outputs = vec![0.0; network.num_outputs];
+		for _ in 0..10 {
+			let inputs: Vec<f32> = (0..network.num_sensors).map(|_| rng.gen_range(-2.0, 2.0)).collect();
+			compiled.activate(&inputs, &mut outputs);
+			for (output, expected) in outputs.iter().zip(reference_outputs(network, &inputs)) {
+				assert!((output - expected).abs() < 1e-5, "{} != {}", output, expected);
+			}
+		}
+	}
+
+	fn node(innovation: usize, node_type: Type, activation: Activation) -> Node {
+		Node {
+			innovation,
+			node_type,
+			activation
+		}
+	}
+
+	fn connection(innovation: usize, input: usize, output: usize, weight: f32, enabled: bool) -> Connection {
+		Connection {
+			innovation,
+			input,
+			output,
+			weight,
+			enabled
+		}
+	}
+
+	#[test]
+	fn matches_reference_on_hand_built_network() {
+		// node 5 has no incoming connections, node 7 doesn't lead to an output
+		let network = Network {
+			node_genes: vec![
+				node(0, Type::Sensor, Activation::Identity),
+				node(1, Type::Sensor, Activation::Tanh),
+				node(2, Type::Bias, Activation::Identity),
+				node(3, Type::Output, Activation::Sigmoid),
+				node(4, Type::Output, Activation::Identity),
+				node(5, Type::Hidden, Activation::Gaussian),
+				node(6, Type::Hidden, Activation::Relu),
+				node(7, Type::Hidden, Activation::Sine)
+			],
+			connection_genes: vec![
+				connection(0, 0, 6, 1.5, true),
+				connection(1, 1, 6, -0.7, true),
+				connection(2, 2, 6, 0.3, true),
+				connection(3, 6, 3, 2.0, true),
+				connection(4, 5, 3, -1.2, true),
+				connection(5, 5, 4, 0.8, true),
+				connection(6, 0, 4, 0.5, false),
+				connection(7, 1, 4, -0.4, true),
+				connection(8, 6, 7, 1.0, true)
+			],
+			num_sensors: 2,
+			num_outputs: 2,
+			recurrent: false
+		};
+		network.validate().unwrap();
+		assert_matches_reference(&network, &mut StdRng::seed_from_u64(0));
+	}
+
+	#[test]
+	fn matches_reference_on_evolved_networks() {
+		let config = NeatConfig::builder()
+			.seed(Some(9))
+			.activation_options(Activation::ALL.to_vec())
+			.activation_mutation_probability(0.2)
+			.build()
+			.unwrap();
+		let mut neat = Neat::with_config(2, 1, config).unwrap();
+		neat.generate_population(50);
+		for _ in 0..15 {
+			neat.next_generation(&mut ParallelEvaluator(xor_fitness));
+		}
+
+		let mut rng = StdRng::seed_from_u64(0);
+		for network in &neat.population {
+			assert_matches_reference(network, &mut rng);
+		}
+	}
+}
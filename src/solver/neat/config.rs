@@ -0,0 +1,557 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::Activation;
+
+#[derive(Debug)]
+pub enum ConfigError {
+	Io(std::io::Error),
+	Parse { line: usize, message: String },
+	Invalid { key: &'static str, message: String }
+}
+
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ConfigError::Io(error) => write!(f, "could not read config: {}", error),
+			ConfigError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+			ConfigError::Invalid { key, message } => write!(f, "invalid value for {}: {}", key, message)
+		}
+	}
+}
+
+impl std::error::Error for ConfigError {}
+
+// which hall of fame members new genomes are evaluated against each generation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HallOfFameSampling {
+	All,
+	Recent, // the most recent champions
+	Uniform // champions picked at random without replacement
+}
+
+impl fmt::Display for HallOfFameSampling {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", match self {
+			HallOfFameSampling::All => "all",
+			HallOfFameSampling::Recent => "recent",
+			HallOfFameSampling::Uniform => "uniform"
+		})
+	}
+}
+
+impl FromStr for HallOfFameSampling {
+	type Err = String;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		match name {
+			"all" => Ok(HallOfFameSampling::All),
+			"recent" => Ok(HallOfFameSampling::Recent),
+			"uniform" => Ok(HallOfFameSampling::Uniform),
+			_ => Err(format!("unknown hall of fame sampling {}", name))
+		}
+	}
+}
+
+impl From<std::io::Error> for ConfigError {
+	fn from(error: std::io::Error) -> Self {
+		ConfigError::Io(error)
+	}
+}
+
+// config files are INI-like: one `key = value` pair per line, `#` starts a comment,
+// `[section]` headers may be used to group keys and tuples are comma separated
+#[derive(Clone, Debug, PartialEq)]
+pub struct NeatConfig {
+	pub weight_mutation_probability: f64,
+	pub weight_randomization_probability: f64,
+	pub weight_mutation_amount: f32,
+	pub no_crossover_probablility: f64,
+	pub interspecies_mating_rate: f64,
+	pub node_mutation_probability: f64,
+	pub connection_mutation_probability: f64,
+	pub activation_mutation_probability: f64,
+	pub activation_options: Vec<Activation>, // activations a hidden node can mutate to
+	pub sensor_activation: Activation,
+	pub output_activation: Activation,
+	pub hidden_activation: Activation,
+	pub compatability_constants: (f64, f64, f64),
+	pub minimum_speciation_distance: f64,
+	pub target_species: Option<usize>,
+	pub speciation_distance_step: f64,
+	pub speciation_distance_bounds: (f64, f64),
+	pub stagnant_generation_limit: i32,
+	pub population_stagnation_limit: i32,
+	pub hall_of_fame_size: usize, // past champions kept, 0 disables the hall of fame
+	pub hall_of_fame_sampling: HallOfFameSampling,
+	pub hall_of_fame_samples: usize, // members drawn each generation unless sampling all
+	pub recurrent: bool,
+	pub num_threads: usize, // threads a ParallelEvaluator uses, 0 uses every available core
+	pub seed: Option<u64> // a random seed is picked when none is given
+}
+
+impl Default for NeatConfig {
+	fn default() -> Self {
+		NeatConfig {
+			weight_mutation_probability: 0.8,
+			weight_randomization_probability: 0.1,
+			weight_mutation_amount: 0.2,
+			no_crossover_probablility: 0.25,
+			interspecies_mating_rate: 0.001,
+			node_mutation_probability: 0.03,
+			connection_mutation_probability: 0.05,
+			activation_mutation_probability: 0.0,
+			activation_options: Activation::ALL.to_vec(),
+			sensor_activation: Activation::Identity,
+			output_activation: Activation::Sigmoid,
+			hidden_activation: Activation::Relu,
+			compatability_constants: (1.0, 1.0, 0.4),
+			minimum_speciation_distance: 3.0,
+			target_species: None,
+			speciation_distance_step: 0.3,
+			speciation_distance_bounds: (0.3, 10.0),
+			stagnant_generation_limit: 15,
+			population_stagnation_limit: 20,
+			hall_of_fame_size: 0,
+			hall_of_fame_sampling: HallOfFameSampling::Uniform,
+			hall_of_fame_samples: 5,
+			recurrent: false,
+			num_threads: 1,
+			seed: None
+		}
+	}
+}
+
+impl NeatConfig {
+	pub fn builder() -> NeatConfigBuilder {
+		NeatConfigBuilder {
+			config: NeatConfig::default()
+		}
+	}
+
+	pub fn from_file<P: AsRef<Path>>(path: P) -> Result<NeatConfig, ConfigError> {
+		fs::read_to_string(path)?.parse()
+	}
+
+	pub fn validate(&self) -> Result<(), ConfigError> {
+		check_probability("weight_mutation_probability", self.weight_mutation_probability)?;
+		check_probability("weight_randomization_probability", self.weight_randomization_probability)?;
+		check_probability("no_crossover_probablility", self.no_crossover_probablility)?;
+		check_probability("interspecies_mating_rate", self.interspecies_mating_rate)?;
+		check_probability("node_mutation_probability", self.node_mutation_probability)?;
+		check_probability("connection_mutation_probability", self.connection_mutation_probability)?;
+		check_probability("activation_mutation_probability", self.activation_mutation_probability)?;
+		if self.activation_mutation_probability > 0.0 && self.activation_options.is_empty() {
+			return Err(invalid("activation_options", "activations can mutate but there are no options"));
+		}
+
+		check_positive("weight_mutation_amount", self.weight_mutation_amount as f64)?;
+		check_positive("minimum_speciation_distance", self.minimum_speciation_distance)?;
+		check_positive("speciation_distance_step", self.speciation_distance_step)?;
+		check_positive("speciation_distance_bounds", self.speciation_distance_bounds.0)?;
+		if self.speciation_distance_bounds.0 > self.speciation_distance_bounds.1 {
+			return Err(invalid("speciation_distance_bounds", "lower bound is above upper bound"));
+		}
+
+		let (c1, c2, c3) = self.compatability_constants;
+		if c1 < 0.0 || c2 < 0.0 || c3 < 0.0 || c1 + c2 + c3 <= 0.0 {
+			return Err(invalid("compatability_constants", "constants must be non-negative and not all zero"));
+		}
+
+		if self.target_species == Some(0) {
+			return Err(invalid("target_species", "must be at least 1"));
+		}
+		if self.stagnant_generation_limit <= 0 {
+			return Err(invalid("stagnant_generation_limit", "must be positive"));
+		}
+		if self.population_stagnation_limit <= 0 {
+			return Err(invalid("population_stagnation_limit", "must be positive"));
+		}
+		if self.hall_of_fame_size > 0 && self.hall_of_fame_sampling != HallOfFameSampling::All && self.hall_of_fame_samples == 0 {
+			return Err(invalid("hall_of_fame_samples", "must be at least 1 when the hall of fame is sampled"));
+		}
+
+		Ok(())
+	}
+
+	fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+		match key {
+			"weight_mutation_probability" => self.weight_mutation_probability = parse_value(value)?,
+			"weight_randomization_probability" => self.weight_randomization_probability = parse_value(value)?,
+			"weight_mutation_amount" => self.weight_mutation_amount = parse_value(value)?,
+			"no_crossover_probablility" => self.no_crossover_probablility = parse_value(value)?,
+			"interspecies_mating_rate" => self.interspecies_mating_rate = parse_value(value)?,
+			"node_mutation_probability" => self.node_mutation_probability = parse_value(value)?,
+			"connection_mutation_probability" => self.connection_mutation_probability = parse_value(value)?,
+			"activation_mutation_probability" => self.activation_mutation_probability = parse_value(value)?,
+			"activation_options" => self.activation_options = match value {
+				"none" => Vec::new(),
+				_ => value.split(',').map(|x| x.trim().parse()).collect::<Result<Vec<Activation>, String>>()?
+			},
+			"sensor_activation" => self.sensor_activation = value.parse()?,
+			"output_activation" => self.output_activation = value.parse()?,
+			"hidden_activation" => self.hidden_activation = value.parse()?,
+			"compatability_constants" => {
+				let values = parse_tuple(value, 3)?;
+				self.compatability_constants = (values[0], values[1], values[2]);
+			},
+			"minimum_speciation_distance" => self.minimum_speciation_distance = parse_value(value)?,
+			"target_species" => self.target_species = match value {
+				"none" => None,
+				_ => Some(parse_value(value)?)
+			},
+			"speciation_distance_step" => self.speciation_distance_step = parse_value(value)?,
+			"speciation_distance_bounds" => {
+				let values = parse_tuple(value, 2)?;
+				self.speciation_distance_bounds = (values[0], values[1]);
+			},
+			"stagnant_generation_limit" => self.stagnant_generation_limit = parse_value(value)?,
+			"population_stagnation_limit" => self.population_stagnation_limit = parse_value(value)?,
+			"hall_of_fame_size" => self.hall_of_fame_size = parse_value(value)?,
+			"hall_of_fame_sampling" => self.hall_of_fame_sampling = value.parse()?,
+			"hall_of_fame_samples" => self.hall_of_fame_samples = parse_value(value)?,
+			"recurrent" => self.recurrent = parse_value(value)?,
+			"num_threads" => self.num_threads = parse_value(value)?,
+			"seed" => self.seed = match value {
+				"none" => None,
+				_ => Some(parse_value(value)?)
+			},
+			_ => return Err(format!("unknown key {}", key))
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for NeatConfig {
+	type Err = ConfigError;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let mut config = NeatConfig::default();
+		for (i, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap().trim();
+			if line.is_empty() || (line.starts_with('[') && line.ends_with(']')) {
+				continue;
+			}
+
+			let (key, value) = match line.find('=') {
+				Some(split) => (line[..split].trim(), line[split + 1..].trim()),
+				None => return Err(ConfigError::Parse { line: i + 1, message: format!("expected key = value, found {}", line) })
+			};
+			config.set(key, value).map_err(|message| ConfigError::Parse { line: i + 1, message })?;
+		}
+
+		config.validate()?;
+		Ok(config)
+	}
+}
+
+impl fmt::Display for NeatConfig {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "[mutation]")?;
+		writeln!(f, "weight_mutation_probability = {}", self.weight_mutation_probability)?;
+		writeln!(f, "weight_randomization_probability = {}", self.weight_randomization_probability)?;
+		writeln!(f, "weight_mutation_amount = {}", self.weight_mutation_amount)?;
+		writeln!(f, "node_mutation_probability = {}", self.node_mutation_probability)?;
+		writeln!(f, "connection_mutation_probability = {}", self.connection_mutation_probability)?;
+		writeln!(f, "activation_mutation_probability = {}", self.activation_mutation_probability)?;
+		if self.activation_options.is_empty() {
+			writeln!(f, "activation_options = none")?;
+		}
+		else {
+			let names: Vec<&str> = self.activation_options.iter().map(|a| a.get_name()).collect();
+			writeln!(f, "activation_options = {}", names.join(", "))?;
+		}
+		writeln!(f)?;
+		writeln!(f, "[activation]")?;
+		writeln!(f, "sensor_activation = {}", self.sensor_activation)?;
+		writeln!(f, "output_activation = {}", self.output_activation)?;
+		writeln!(f, "hidden_activation = {}", self.hidden_activation)?;
+		writeln!(f)?;
+		writeln!(f, "[reproduction]")?;
+		writeln!(f, "no_crossover_probablility = {}", self.no_crossover_probablility)?;
+		writeln!(f, "interspecies_mating_rate = {}", self.interspecies_mating_rate)?;
+		writeln!(f, "stagnant_generation_limit = {}", self.stagnant_generation_limit)?;
+		writeln!(f, "population_stagnation_limit = {}", self.population_stagnation_limit)?;
+		writeln!(f)?;
+		writeln!(f, "[speciation]")?;
+		let (c1, c2, c3) = self.compatability_constants;
+		writeln!(f, "compatability_constants = {}, {}, {}", c1, c2, c3)?;
+		writeln!(f, "minimum_speciation_distance = {}", self.minimum_speciation_distance)?;
+		match self.target_species {
+			Some(target) => writeln!(f, "target_species = {}", target)?,
+			None => writeln!(f, "target_species = none")?
+		}
+		writeln!(f, "speciation_distance_step = {}", self.speciation_distance_step)?;
+		writeln!(f, "speciation_distance_bounds = {}, {}", self.speciation_distance_bounds.0, self.speciation_distance_bounds.1)?;
+		writeln!(f)?;
+		writeln!(f, "[hall_of_fame]")?;
+		writeln!(f, "hall_of_fame_size = {}", self.hall_of_fame_size)?;
+		writeln!(f, "hall_of_fame_sampling = {}", self.hall_of_fame_sampling)?;
+		writeln!(f, "hall_of_fame_samples = {}", self.hall_of_fame_samples)?;
+		writeln!(f)?;
+		writeln!(f, "[run]")?;
+		writeln!(f, "recurrent = {}", self.recurrent)?;
+		writeln!(f, "num_threads = {}", self.num_threads)?;
+		match self.seed {
+			Some(seed) => writeln!(f, "seed = {}", seed),
+			None => writeln!(f, "seed = none")
+		}
+	}
+}
+
+pub struct NeatConfigBuilder {
+	config: NeatConfig
+}
+
+// there is a setter for every field, whether or not the binary sets it
+#[allow(dead_code)]
+impl NeatConfigBuilder {
+	pub fn weight_mutation_probability(mut self, probability: f64) -> Self {
+		self.config.weight_mutation_probability = probability;
+		self
+	}
+
+	pub fn weight_randomization_probability(mut self, probability: f64) -> Self {
+		self.config.weight_randomization_probability = probability;
+		self
+	}
+
+	pub fn weight_mutation_amount(mut self, amount: f32) -> Self {
+		self.config.weight_mutation_amount = amount;
+		self
+	}
+
+	pub fn no_crossover_probablility(mut self, probability: f64) -> Self {
+		self.config.no_crossover_probablility = probability;
+		self
+	}
+
+	pub fn interspecies_mating_rate(mut self, rate: f64) -> Self {
+		self.config.interspecies_mating_rate = rate;
+		self
+	}
+
+	pub fn node_mutation_probability(mut self, probability: f64) -> Self {
+		self.config.node_mutation_probability = probability;
+		self
+	}
+
+	pub fn connection_mutation_probability(mut self, probability: f64) -> Self {
+		self.config.connection_mutation_probability = probability;
+		self
+	}
+
+	pub fn activation_mutation_probability(mut self, probability: f64) -> Self {
+		self.config.activation_mutation_probability = probability;
+		self
+	}
+
+	pub fn activation_options(mut self, options: Vec<Activation>) -> Self {
+		self.config.activation_options = options;
+		self
+	}
+
+	pub fn sensor_activation(mut self, activation: Activation) -> Self {
+		self.config.sensor_activation = activation;
+		self
+	}
+
+	pub fn output_activation(mut self, activation: Activation) -> Self {
+		self.config.output_activation = activation;
+		self
+	}
+
+	pub fn hidden_activation(mut self, activation: Activation) -> Self {
+		self.config.hidden_activation = activation;
+		self
+	}
+
+	pub fn compatability_constants(mut self, constants: (f64, f64, f64)) -> Self {
+		self.config.compatability_constants = constants;
+		self
+	}
+
+	pub fn minimum_speciation_distance(mut self, distance: f64) -> Self {
+		self.config.minimum_speciation_distance = distance;
+		self
+	}
+
+	pub fn target_species(mut self, target_species: Option<usize>) -> Self {
+		self.config.target_species = target_species;
+		self
+	}
+
+	pub fn speciation_distance_step(mut self, step: f64) -> Self {
+		self.config.speciation_distance_step = step;
+		self
+	}
+
+	pub fn speciation_distance_bounds(mut self, bounds: (f64, f64)) -> Self {
+		self.config.speciation_distance_bounds = bounds;
+		self
+	}
+
+	pub fn stagnant_generation_limit(mut self, limit: i32) -> Self {
+		self.config.stagnant_generation_limit = limit;
+		self
+	}
+
+	pub fn population_stagnation_limit(mut self, limit: i32) -> Self {
+		self.config.population_stagnation_limit = limit;
+		self
+	}
+
+	pub fn hall_of_fame_size(mut self, size: usize) -> Self {
+		self.config.hall_of_fame_size = size;
+		self
+	}
+
+	pub fn hall_of_fame_sampling(mut self, sampling: HallOfFameSampling) -> Self {
+		self.config.hall_of_fame_sampling = sampling;
+		self
+	}
+
+	pub fn hall_of_fame_samples(mut self, samples: usize) -> Self {
+		self.config.hall_of_fame_samples = samples;
+		self
+	}
+
+	pub fn recurrent(mut self, recurrent: bool) -> Self {
+		self.config.recurrent = recurrent;
+		self
+	}
+
+	// only used by ParallelEvaluator, a plain closure always runs on one thread
+	pub fn num_threads(mut self, num_threads: usize) -> Self {
+		self.config.num_threads = num_threads;
+		self
+	}
+
+	pub fn seed(mut self, seed: Option<u64>) -> Self {
+		self.config.seed = seed;
+		self
+	}
+
+	pub fn build(self) -> Result<NeatConfig, ConfigError> {
+		self.config.validate()?;
+		Ok(self.config)
+	}
+}
+
+fn invalid(key: &'static str, message: &str) -> ConfigError {
+	ConfigError::Invalid { key, message: message.to_string() }
+}
+
+fn check_probability(key: &'static str, value: f64) -> Result<(), ConfigError> {
+	if (0.0..=1.0).contains(&value) {
+		Ok(())
+	}
+	else {
+		Err(invalid(key, &format!("{} is not a probability in [0, 1]", value)))
+	}
+}
+
+fn check_positive(key: &'static str, value: f64) -> Result<(), ConfigError> {
+	if value > 0.0 && value.is_finite() {
+		Ok(())
+	}
+	else {
+		Err(invalid(key, &format!("{} is not positive", value)))
+	}
+}
+
+fn parse_value<T: FromStr>(value: &str) -> Result<T, String> {
+	value.parse().map_err(|_| format!("could not parse {}", value))
+}
+
+fn parse_tuple(value: &str, length: usize) -> Result<Vec<f64>, String> {
+	let value = value.trim_matches(|c| c == '(' || c == ')' || c == '[' || c == ']');
+	let values = value.split(',')
+		.map(|x| parse_value(x.trim()))
+		.collect::<Result<Vec<f64>, String>>()?;
+	if values.len() != length {
+		return Err(format!("expected {} values, found {}", length, values.len()));
+	}
+	Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse_error(text: &str) -> ConfigError {
+		text.parse::<NeatConfig>().unwrap_err()
+	}
+
+	#[test]
+	fn display_round_trips_exactly() {
+		let config = NeatConfig::builder()
+			.weight_mutation_probability(1.0 / 3.0)
+			.weight_randomization_probability(0.0)
+			.weight_mutation_amount(0.15)
+			.no_crossover_probablility(1.0)
+			.interspecies_mating_rate(1e-7)
+			.node_mutation_probability(0.1 + 0.2)
+			.connection_mutation_probability(0.07)
+			.activation_mutation_probability(0.05)
+			.activation_options(vec![Activation::Tanh, Activation::Step])
+			.sensor_activation(Activation::Abs)
+			.output_activation(Activation::Gaussian)
+			.hidden_activation(Activation::Sine)
+			.compatability_constants((2.5, 0.0, 0.125))
+			.minimum_speciation_distance(4.2)
+			.target_species(Some(12))
+			.speciation_distance_step(0.05)
+			.speciation_distance_bounds((0.5, 8.0))
+			.stagnant_generation_limit(7)
+			.population_stagnation_limit(30)
+			.hall_of_fame_size(20)
+			.hall_of_fame_sampling(HallOfFameSampling::Recent)
+			.hall_of_fame_samples(3)
+			.recurrent(true)
+			.num_threads(4)
+			.seed(Some(u64::MAX))
+			.build()
+			.unwrap();
+		assert_eq!(config.to_string().parse::<NeatConfig>().unwrap(), config);
+
+		let config = NeatConfig::builder().activation_options(Vec::new()).build().unwrap();
+		assert_eq!(config.to_string().parse::<NeatConfig>().unwrap(), config);
+		assert_eq!(NeatConfig::default().to_string().parse::<NeatConfig>().unwrap(), NeatConfig::default());
+	}
+
+	#[test]
+	fn missing_keys_keep_their_defaults() {
+		let config: NeatConfig = "# only the seed\n[run]\nseed = 3\n".parse().unwrap();
+		assert_eq!(config, NeatConfig { seed: Some(3), ..NeatConfig::default() });
+	}
+
+	#[test]
+	fn rejects_unknown_key() {
+		assert!(matches!(parse_error("seed = 1\nmutation_rate = 0.5"), ConfigError::Parse { line: 2, .. }));
+		assert!(matches!(parse_error("seed 1"), ConfigError::Parse { line: 1, .. }));
+	}
+
+	#[test]
+	fn rejects_bad_values() {
+		assert!(matches!(parse_error("node_mutation_probability = 1.5"), ConfigError::Invalid { key: "node_mutation_probability", .. }));
+		assert!(matches!(parse_error("interspecies_mating_rate = -0.1"), ConfigError::Invalid { key: "interspecies_mating_rate", .. }));
+		assert!(matches!(parse_error("speciation_distance_bounds = 5.0, 1.0"), ConfigError::Invalid { key: "speciation_distance_bounds", .. }));
+		assert!(matches!(parse_error("speciation_distance_bounds = 1.0"), ConfigError::Parse { .. }));
+		assert!(matches!(parse_error("hidden_activation = softmax"), ConfigError::Parse { .. }));
+		assert!(matches!(parse_error("target_species = many"), ConfigError::Parse { .. }));
+		assert!(matches!(
+			parse_error("activation_mutation_probability = 0.1\nactivation_options = none"),
+			ConfigError::Invalid { key: "activation_options", .. }
+		));
+	}
+
+	#[test]
+	fn builder_validates() {
+		assert!(NeatConfig::builder().no_crossover_probablility(1.5).build().is_err());
+		assert!(NeatConfig::builder().target_species(Some(0)).build().is_err());
+		assert!(NeatConfig::builder().weight_mutation_amount(f32::NAN).build().is_err());
+	}
+}
@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::Path;
+
+use super::genome::GenomeError;
+use super::{Network, Type};
+
+// weights at or above this magnitude are drawn with the thickest line
+const MAX_DRAWN_WEIGHT: f32 = 4.0;
+
+impl Network {
+	pub fn save_dot<P: AsRef<Path>>(&self, path: P) -> Result<(), GenomeError> {
+		fs::write(path, self.to_dot())?;
+		Ok(())
+	}
+
+	// graphviz source for the network, drawn bottom to top from the sensors to the outputs.
+	// positive weights are blue and negative weights red, with thicker lines for larger magnitudes
+	pub fn to_dot(&self) -> String {
+		let mut dot = String::from("digraph network {\n");
+		dot += "\trankdir=BT;\n";
+		dot += "\tnode [fontname=\"Helvetica\", fontsize=10];\n";
+		dot += "\tedge [fontname=\"Helvetica\", fontsize=8];\n\n";
+
+		dot += "\tsubgraph inputs {\n\t\trank=min;\n";
+		for node in self.node_genes.iter().filter(|n| matches!(n.node_type, Type::Sensor | Type::Bias)) {
+			let (label, shape) = match node.node_type {
+				Type::Bias => ("bias".to_string(), "diamond"),
+				_ => (format!("in {}", node.innovation), "box")
+			};
+			dot += &format!("\t\tn{} [label=\"{}\", shape={}];\n", node.innovation, label, shape);
+		}
+		dot += "\t}\n";
+
+		dot += "\tsubgraph outputs {\n\t\trank=max;\n";
+		for node in self.node_genes.iter().filter(|n| matches!(n.node_type, Type::Output)) {
+			dot += &format!(
+				"\t\tn{} [label=\"out {}\\n{}\", shape=doublecircle];\n",
+				node.innovation, node.innovation - self.num_sensors - 1, node.activation
+			);
+		}
+		dot += "\t}\n\n";
+
+		for node in self.node_genes.iter().filter(|n| matches!(n.node_type, Type::Hidden)) {
+			dot += &format!("\tn{} [label=\"{}\\n{}\", shape=circle];\n", node.innovation, node.innovation, node.activation);
+		}
+		dot += "\n";
+
+		for connection in &self.connection_genes {
+			let style = if connection.enabled {
+				let color = if connection.weight >= 0.0 {"blue"} else {"red"};
+				let width = 0.5 + 2.5 * (connection.weight.abs() / MAX_DRAWN_WEIGHT).min(1.0);
+				format!("color={}, penwidth={:.2}", color, width)
+			}
+			else {
+				"color=gray, style=dashed".to_string()
+			};
+			dot += &format!(
+				"\tn{} -> n{} [label=\"{}\", tooltip=\"weight {:.3}\", {}];\n",
+				connection.input, connection.output, connection.innovation, connection.weight, style
+			);
+		}
+
+		dot += "}\n";
+		dot
+	}
+}
@@ -0,0 +1,239 @@
+use super::{Activation, Connection, Network, Node, Type};
+
+// little endian byte encoding shared by checkpoints and genome files
+
+pub(crate) struct ByteWriter {
+	bytes: Vec<u8>
+}
+
+impl ByteWriter {
+	pub fn new() -> Self {
+		ByteWriter {
+			bytes: Vec::new()
+		}
+	}
+
+	pub fn into_bytes(self) -> Vec<u8> {
+		self.bytes
+	}
+
+	pub fn write_bytes(&mut self, bytes: &[u8]) {
+		self.bytes.extend_from_slice(bytes);
+	}
+
+	pub fn write_u8(&mut self, value: u8) {
+		self.bytes.push(value);
+	}
+
+	pub fn write_bool(&mut self, value: bool) {
+		self.write_u8(value as u8);
+	}
+
+	pub fn write_u32(&mut self, value: u32) {
+		self.write_bytes(&value.to_le_bytes());
+	}
+
+	pub fn write_i32(&mut self, value: i32) {
+		self.write_bytes(&value.to_le_bytes());
+	}
+
+	pub fn write_u64(&mut self, value: u64) {
+		self.write_bytes(&value.to_le_bytes());
+	}
+
+	pub fn write_usize(&mut self, value: usize) {
+		self.write_u64(value as u64);
+	}
+
+	pub fn write_f32(&mut self, value: f32) {
+		self.write_bytes(&value.to_le_bytes());
+	}
+
+	pub fn write_f64(&mut self, value: f64) {
+		self.write_bytes(&value.to_le_bytes());
+	}
+
+	pub fn write_str(&mut self, value: &str) {
+		self.write_usize(value.len());
+		self.write_bytes(value.as_bytes());
+	}
+
+	pub fn write_connection(&mut self, connection: &Connection) {
+		self.write_usize(connection.innovation);
+		self.write_usize(connection.input);
+		self.write_usize(connection.output);
+		self.write_f32(connection.weight);
+		self.write_bool(connection.enabled);
+	}
+
+	pub fn write_connections(&mut self, connections: &[Connection]) {
+		self.write_usize(connections.len());
+		for connection in connections {
+			self.write_connection(connection);
+		}
+	}
+
+	pub fn write_node(&mut self, node: &Node) {
+		self.write_usize(node.innovation);
+		self.write_u8(match node.node_type {
+			Type::Sensor => 0,
+			Type::Output => 1,
+			Type::Hidden => 2,
+			Type::Bias => 3
+		});
+		self.write_u8(node.activation.to_id());
+	}
+
+	pub fn write_network(&mut self, network: &Network) {
+		self.write_usize(network.num_sensors);
+		self.write_usize(network.num_outputs);
+		self.write_bool(network.recurrent);
+		self.write_usize(network.node_genes.len());
+		for node in &network.node_genes {
+			self.write_node(node);
+		}
+		self.write_connections(&network.connection_genes);
+	}
+}
+
+pub(crate) struct ByteReader<'a> {
+	bytes: &'a [u8],
+	position: usize
+}
+
+impl<'a> ByteReader<'a> {
+	pub fn new(bytes: &'a [u8]) -> Self {
+		ByteReader {
+			bytes,
+			position: 0
+		}
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.position == self.bytes.len()
+	}
+
+	pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+		if self.bytes.len() - self.position < length {
+			return Err(format!("unexpected end of data at byte {}", self.position));
+		}
+		let bytes = &self.bytes[self.position..self.position + length];
+		self.position += length;
+		Ok(bytes)
+	}
+
+	fn read_array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+		let mut array = [0; N];
+		array.copy_from_slice(self.read_bytes(N)?);
+		Ok(array)
+	}
+
+	pub fn read_u8(&mut self) -> Result<u8, String> {
+		Ok(self.read_array::<1>()?[0])
+	}
+
+	pub fn read_bool(&mut self) -> Result<bool, String> {
+		match self.read_u8()? {
+			0 => Ok(false),
+			1 => Ok(true),
+			x => Err(format!("invalid boolean {}", x))
+		}
+	}
+
+	pub fn read_u32(&mut self) -> Result<u32, String> {
+		Ok(u32::from_le_bytes(self.read_array()?))
+	}
+
+	pub fn read_i32(&mut self) -> Result<i32, String> {
+		Ok(i32::from_le_bytes(self.read_array()?))
+	}
+
+	pub fn read_u64(&mut self) -> Result<u64, String> {
+		Ok(u64::from_le_bytes(self.read_array()?))
+	}
+
+	pub fn read_usize(&mut self) -> Result<usize, String> {
+		let value = self.read_u64()?;
+		if value > usize::MAX as u64 {
+			return Err(format!("value {} does not fit in usize", value));
+		}
+		Ok(value as usize)
+	}
+
+	// reads a length prefix, rejecting lengths that could not possibly fit in the remaining data
+	pub fn read_length(&mut self, min_item_size: usize) -> Result<usize, String> {
+		let length = self.read_usize()?;
+		if length.saturating_mul(min_item_size) > self.bytes.len() - self.position {
+			return Err(format!("length {} exceeds the remaining data", length));
+		}
+		Ok(length)
+	}
+
+	pub fn read_f32(&mut self) -> Result<f32, String> {
+		Ok(f32::from_le_bytes(self.read_array()?))
+	}
+
+	pub fn read_f64(&mut self) -> Result<f64, String> {
+		Ok(f64::from_le_bytes(self.read_array()?))
+	}
+
+	pub fn read_str(&mut self) -> Result<String, String> {
+		let length = self.read_length(1)?;
+		String::from_utf8(self.read_bytes(length)?.to_vec()).map_err(|_| "invalid utf-8 string".to_string())
+	}
+
+	pub fn read_connection(&mut self) -> Result<Connection, String> {
+		Ok(Connection {
+			innovation: self.read_usize()?,
+			input: self.read_usize()?,
+			output: self.read_usize()?,
+			weight: self.read_f32()?,
+			enabled: self.read_bool()?
+		})
+	}
+
+	pub fn read_connections(&mut self) -> Result<Vec<Connection>, String> {
+		let length = self.read_length(29)?;
+		let mut connections = Vec::with_capacity(length);
+		for _ in 0..length {
+			connections.push(self.read_connection()?);
+		}
+		Ok(connections)
+	}
+
+	pub fn read_node(&mut self) -> Result<Node, String> {
+		let innovation = self.read_usize()?;
+		let node_type = match self.read_u8()? {
+			0 => Type::Sensor,
+			1 => Type::Output,
+			2 => Type::Hidden,
+			3 => Type::Bias,
+			x => return Err(format!("invalid node type {}", x))
+		};
+		let id = self.read_u8()?;
+		let activation = Activation::from_id(id).ok_or_else(|| format!("invalid activation {}", id))?;
+		Ok(Node {
+			innovation,
+			node_type,
+			activation
+		})
+	}
+
+	pub fn read_network(&mut self) -> Result<Network, String> {
+		let num_sensors = self.read_usize()?;
+		let num_outputs = self.read_usize()?;
+		let recurrent = self.read_bool()?;
+		let num_nodes = self.read_length(10)?;
+		let mut node_genes = Vec::with_capacity(num_nodes);
+		for _ in 0..num_nodes {
+			node_genes.push(self.read_node()?);
+		}
+		Ok(Network {
+			node_genes,
+			connection_genes: self.read_connections()?,
+			num_sensors,
+			num_outputs,
+			recurrent
+		})
+	}
+}
@@ -0,0 +1,58 @@
+use super::Network;
+
+pub trait FitnessEvaluator {
+	fn evaluate(&mut self, network: &mut Network) -> f32;
+
+	// override to score genomes relative to each other, e.g. with a tournament.
+	// the returned fitnesses must be in population order. the default ignores num_threads
+	fn evaluate_population(&mut self, population: &mut [Network], _num_threads: usize) -> Vec<f32> {
+		population.iter_mut().map(|network| self.evaluate(network)).collect()
+	}
+
+	// called before each generation is evaluated with the hall of fame members sampled for it.
+	// evaluators that pit genomes against each other can use them as extra opponents
+	fn set_hall_of_fame(&mut self, _opponents: &[Network]) {}
+
+	// called before each generation is evaluated with a seed drawn from the run's rng.
+	// evaluators that use randomness should reseed from it, so that seeded runs and runs
+	// resumed from a checkpoint make the same choices
+	fn set_seed(&mut self, _seed: u64) {}
+}
+
+impl<F: FnMut(&mut Network) -> f32> FitnessEvaluator for F {
+	fn evaluate(&mut self, network: &mut Network) -> f32 {
+		self(network)
+	}
+}
+
+// evaluates the population across the configured number of threads,
+// which needs a fitness function that can be shared between them
+pub struct ParallelEvaluator<F: Fn(&mut Network) -> f32 + Sync>(pub F);
+
+impl<F: Fn(&mut Network) -> f32 + Sync> FitnessEvaluator for ParallelEvaluator<F> {
+	fn evaluate(&mut self, network: &mut Network) -> f32 {
+		(self.0)(network)
+	}
+
+	fn evaluate_population(&mut self, population: &mut [Network], num_threads: usize) -> Vec<f32> {
+		evaluate_in_parallel(population, num_threads, &self.0)
+	}
+}
+
+pub fn evaluate_in_parallel<F: Fn(&mut Network) -> f32 + Sync>(population: &mut [Network], num_threads: usize, fitness_function: &F) -> Vec<f32> {
+	let num_threads = num_threads.min(population.len());
+	if num_threads <= 1 {
+		return population.iter_mut().map(fitness_function).collect();
+	}
+
+	// each thread evaluates a contiguous chunk so results come back in population order
+	let chunk_size = population.len().div_ceil(num_threads);
+	std::thread::scope(|scope| {
+		let workers: Vec<_> = population.chunks_mut(chunk_size)
+			.map(|chunk| scope.spawn(move || chunk.iter_mut().map(fitness_function).collect::<Vec<f32>>()))
+			.collect();
+		workers.into_iter()
+			.flat_map(|worker| worker.join().expect("fitness evaluation panicked"))
+			.collect()
+	})
+}
@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use super::encoding::{ByteReader, ByteWriter};
+use super::{Activation, Connection, Network, Node, Type};
+
+const MAGIC: &[u8; 8] = b"NEATGENO";
+const VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum GenomeError {
+	Io(std::io::Error),
+	Parse(String),
+	Invalid(String)
+}
+
+impl fmt::Display for GenomeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			GenomeError::Io(error) => write!(f, "could not access genome: {}", error),
+			GenomeError::Parse(message) => write!(f, "could not parse genome: {}", message),
+			GenomeError::Invalid(message) => write!(f, "invalid genome: {}", message)
+		}
+	}
+}
+
+impl std::error::Error for GenomeError {}
+
+impl From<std::io::Error> for GenomeError {
+	fn from(error: std::io::Error) -> Self {
+		GenomeError::Io(error)
+	}
+}
+
+impl Network {
+	pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<(), GenomeError> {
+		fs::write(path, self.to_json())?;
+		Ok(())
+	}
+
+	pub fn save_binary<P: AsRef<Path>>(&self, path: P) -> Result<(), GenomeError> {
+		fs::write(path, self.to_bytes())?;
+		Ok(())
+	}
+
+	// loads either format, checking the genome fits a game with the given number of sensors and outputs
+	pub fn load<P: AsRef<Path>>(path: P, num_sensors: usize, num_outputs: usize) -> Result<Network, GenomeError> {
+		let bytes = fs::read(path)?;
+		let network = if bytes.starts_with(MAGIC) {
+			Network::from_bytes(&bytes)?
+		}
+		else {
+			let text = String::from_utf8(bytes).map_err(|_| GenomeError::Parse("file is neither a binary genome nor text".to_string()))?;
+			Network::from_json(&text)?
+		};
+
+		if network.num_sensors != num_sensors || network.num_outputs != num_outputs {
+			return Err(GenomeError::Invalid(format!(
+				"genome has {} sensors and {} outputs, expected {} and {}",
+				network.num_sensors, network.num_outputs, num_sensors, num_outputs
+			)));
+		}
+		Ok(network)
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut writer = ByteWriter::new();
+		writer.write_bytes(MAGIC);
+		writer.write_u32(VERSION);
+		writer.write_network(self);
+		writer.into_bytes()
+	}
+
+	pub fn from_bytes(bytes: &[u8]) -> Result<Network, GenomeError> {
+		let mut reader = ByteReader::new(bytes);
+		if reader.read_bytes(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+			return Err(GenomeError::Parse("missing genome header".to_string()));
+		}
+		let version = reader.read_u32().map_err(GenomeError::Parse)?;
+		if version != VERSION {
+			return Err(GenomeError::Parse(format!("unsupported genome version {}", version)));
+		}
+		let network = reader.read_network().map_err(GenomeError::Parse)?;
+		if !reader.is_finished() {
+			return Err(GenomeError::Parse("trailing data after genome".to_string()));
+		}
+		network.validate().map_err(GenomeError::Invalid)?;
+		Ok(network)
+	}
+
+	pub fn to_json(&self) -> String {
+		let mut json = String::from("{\n");
+		json += &format!("\t\"num_sensors\": {},\n", self.num_sensors);
+		json += &format!("\t\"num_outputs\": {},\n", self.num_outputs);
+		json += &format!("\t\"recurrent\": {},\n", self.recurrent);
+
+		json += "\t\"nodes\": [\n";
+		for (i, node) in self.node_genes.iter().enumerate() {
+			let node_type = match node.node_type {
+				Type::Sensor => "sensor",
+				Type::Output => "output",
+				Type::Hidden => "hidden",
+				Type::Bias => "bias"
+			};
+			json += &format!(
+				"\t\t{{\"innovation\": {}, \"type\": \"{}\", \"activation\": \"{}\"}}",
+				node.innovation, node_type, node.activation
+			);
+			json += if i + 1 < self.node_genes.len() {",\n"} else {"\n"};
+		}
+		json += "\t],\n";
+
+		json += "\t\"connections\": [\n";
+		for (i, connection) in self.connection_genes.iter().enumerate() {
+			json += &format!(
+				"\t\t{{\"innovation\": {}, \"input\": {}, \"output\": {}, \"weight\": {:?}, \"enabled\": {}}}",
+				connection.innovation, connection.input, connection.output, connection.weight, connection.enabled
+			);
+			json += if i + 1 < self.connection_genes.len() {",\n"} else {"\n"};
+		}
+		json += "\t]\n}\n";
+
+		json
+	}
+
+	pub fn from_json(text: &str) -> Result<Network, GenomeError> {
+		let value = JsonParser::new(text).parse_document().map_err(GenomeError::Parse)?;
+		let root = value.as_object("genome")?;
+
+		let mut node_genes = Vec::new();
+		for node in get_field(root, "nodes")?.as_array("nodes")? {
+			let node = node.as_object("node")?;
+			let node_type = match get_field(node, "type")?.as_str("type")? {
+				"sensor" => Type::Sensor,
+				"output" => Type::Output,
+				"hidden" => Type::Hidden,
+				"bias" => Type::Bias,
+				x => return Err(GenomeError::Parse(format!("unknown node type {}", x)))
+			};
+			let activation = match node.iter().find(|field| field.0 == "activation") {
+				Some(field) => field.1.as_str("activation")?.parse::<Activation>().map_err(GenomeError::Parse)?,
+				None => node_type.default_activation()
+			};
+			node_genes.push(Node {
+				innovation: get_field(node, "innovation")?.as_index("innovation")?,
+				node_type,
+				activation
+			});
+		}
+
+		let mut connection_genes = Vec::new();
+		for connection in get_field(root, "connections")?.as_array("connections")? {
+			let connection = connection.as_object("connection")?;
+			connection_genes.push(Connection {
+				innovation: get_field(connection, "innovation")?.as_index("innovation")?,
+				input: get_field(connection, "input")?.as_index("input")?,
+				output: get_field(connection, "output")?.as_index("output")?,
+				weight: get_field(connection, "weight")?.as_number("weight")? as f32,
+				enabled: get_field(connection, "enabled")?.as_bool("enabled")?
+			});
+		}
+
+		let network = Network {
+			node_genes,
+			connection_genes,
+			num_sensors: get_field(root, "num_sensors")?.as_index("num_sensors")?,
+			num_outputs: get_field(root, "num_outputs")?.as_index("num_outputs")?,
+			recurrent: match root.iter().find(|field| field.0 == "recurrent") {
+				Some(field) => field.1.as_bool("recurrent")?,
+				None => false
+			}
+		};
+		network.validate().map_err(GenomeError::Invalid)?;
+		Ok(network)
+	}
+
+	// checks the invariants feed_forward relies on
+	pub fn validate(&self) -> Result<(), String> {
+		let fixed_nodes = self.num_sensors + self.num_outputs + 1;
+		if self.node_genes.len() < fixed_nodes {
+			return Err(format!("expected at least {} nodes, found {}", fixed_nodes, self.node_genes.len()));
+		}
+
+		let mut positions = HashMap::with_capacity(self.node_genes.len());
+		for (i, node) in self.node_genes.iter().enumerate() {
+			let type_ok = match node.node_type {
+				Type::Sensor => i < self.num_sensors,
+				Type::Bias => i == self.num_sensors,
+				Type::Output => i > self.num_sensors && i < fixed_nodes,
+				Type::Hidden => i >= fixed_nodes
+			};
+			if !type_ok || (i < fixed_nodes && node.innovation != i) {
+				return Err(format!("node {} is out of place", node.innovation));
+			}
+			if positions.insert(node.innovation, i).is_some() {
+				return Err(format!("duplicate node {}", node.innovation));
+			}
+		}
+
+		let mut innovations = HashMap::with_capacity(self.connection_genes.len());
+		let mut outgoing = vec![Vec::new(); self.node_genes.len()];
+		for connection in &self.connection_genes {
+			if innovations.insert(connection.innovation, ()).is_some() {
+				return Err(format!("duplicate connection {}", connection.innovation));
+			}
+			let input = *positions.get(&connection.input)
+				.ok_or_else(|| format!("connection {} comes from missing node {}", connection.innovation, connection.input))?;
+			let output = *positions.get(&connection.output)
+				.ok_or_else(|| format!("connection {} leads to missing node {}", connection.innovation, connection.output))?;
+			if output <= self.num_sensors {
+				return Err(format!("connection {} leads into a sensor or the bias", connection.innovation));
+			}
+			if !connection.weight.is_finite() {
+				return Err(format!("connection {} has a non-finite weight", connection.innovation));
+			}
+			if connection.enabled {
+				outgoing[input].push(output);
+			}
+		}
+
+		if self.recurrent {
+			return Ok(());
+		}
+
+		// depth first search for a cycle through the enabled connections
+		let mut state = vec![0u8; self.node_genes.len()]; // 0 unvisited, 1 on the stack, 2 finished
+		for start in 0..self.node_genes.len() {
+			if state[start] != 0 {
+				continue;
+			}
+			let mut stack = vec![(start, 0)];
+			state[start] = 1;
+			while let Some((node, next)) = stack.pop() {
+				if next < outgoing[node].len() {
+					stack.push((node, next + 1));
+					let child = outgoing[node][next];
+					match state[child] {
+						0 => {
+							state[child] = 1;
+							stack.push((child, 0));
+						},
+						1 => return Err(format!("enabled connections form a cycle through node {}", self.node_genes[child].innovation)),
+						_ => {}
+					}
+				}
+				else {
+					state[node] = 2;
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+enum JsonValue {
+	Null,
+	Bool(bool),
+	Number(f64),
+	Str(String),
+	Array(Vec<JsonValue>),
+	Object(Vec<(String, JsonValue)>)
+}
+
+impl JsonValue {
+	fn as_object(&self, name: &str) -> Result<&Vec<(String, JsonValue)>, GenomeError> {
+		match self {
+			JsonValue::Object(fields) => Ok(fields),
+			_ => Err(GenomeError::Parse(format!("{} should be an object", name)))
+		}
+	}
+
+	fn as_array(&self, name: &str) -> Result<&Vec<JsonValue>, GenomeError> {
+		match self {
+			JsonValue::Array(values) => Ok(values),
+			_ => Err(GenomeError::Parse(format!("{} should be an array", name)))
+		}
+	}
+
+	fn as_str(&self, name: &str) -> Result<&str, GenomeError> {
+		match self {
+			JsonValue::Str(value) => Ok(value),
+			_ => Err(GenomeError::Parse(format!("{} should be a string", name)))
+		}
+	}
+
+	fn as_bool(&self, name: &str) -> Result<bool, GenomeError> {
+		match self {
+			JsonValue::Bool(value) => Ok(*value),
+			_ => Err(GenomeError::Parse(format!("{} should be true or false", name)))
+		}
+	}
+
+	fn as_number(&self, name: &str) -> Result<f64, GenomeError> {
+		match self {
+			JsonValue::Number(value) => Ok(*value),
+			_ => Err(GenomeError::Parse(format!("{} should be a number", name)))
+		}
+	}
+
+	fn as_index(&self, name: &str) -> Result<usize, GenomeError> {
+		let value = self.as_number(name)?;
+		if value < 0.0 || value.fract() != 0.0 || value > u32::MAX as f64 {
+			return Err(GenomeError::Parse(format!("{} should be a non-negative integer", name)));
+		}
+		Ok(value as usize)
+	}
+}
+
+fn get_field<'a>(fields: &'a [(String, JsonValue)], name: &str) -> Result<&'a JsonValue, GenomeError> {
+	fields.iter()
+		.find(|field| field.0 == name)
+		.map(|field| &field.1)
+		.ok_or_else(|| GenomeError::Parse(format!("missing field {}", name)))
+}
+
+struct JsonParser<'a> {
+	text: &'a [u8],
+	position: usize
+}
+
+impl<'a> JsonParser<'a> {
+	fn new(text: &'a str) -> Self {
+		JsonParser {
+			text: text.as_bytes(),
+			position: 0
+		}
+	}
+
+	fn parse_document(&mut self) -> Result<JsonValue, String> {
+		let value = self.parse_value()?;
+		self.skip_whitespace();
+		if self.position != self.text.len() {
+			return Err(format!("unexpected data at byte {}", self.position));
+		}
+		Ok(value)
+	}
+
+	fn skip_whitespace(&mut self) {
+		while self.position < self.text.len() && self.text[self.position].is_ascii_whitespace() {
+			self.position += 1;
+		}
+	}
+
+	fn peek(&mut self) -> Option<u8> {
+		self.skip_whitespace();
+		self.text.get(self.position).copied()
+	}
+
+	fn expect(&mut self, byte: u8) -> Result<(), String> {
+		if self.peek() == Some(byte) {
+			self.position += 1;
+			Ok(())
+		}
+		else {
+			Err(format!("expected '{}' at byte {}", byte as char, self.position))
+		}
+	}
+
+	fn parse_keyword(&mut self, keyword: &str, value: JsonValue) -> Result<JsonValue, String> {
+		if self.text[self.position..].starts_with(keyword.as_bytes()) {
+			self.position += keyword.len();
+			Ok(value)
+		}
+		else {
+			Err(format!("unexpected data at byte {}", self.position))
+		}
+	}
+
+	fn parse_value(&mut self) -> Result<JsonValue, String> {
+		match self.peek() {
+			Some(b'{') => self.parse_object(),
+			Some(b'[') => self.parse_array(),
+			Some(b'"') => Ok(JsonValue::Str(self.parse_string()?)),
+			Some(b't') => self.parse_keyword("true", JsonValue::Bool(true)),
+			Some(b'f') => self.parse_keyword("false", JsonValue::Bool(false)),
+			Some(b'n') => self.parse_keyword("null", JsonValue::Null),
+			Some(_) => self.parse_number(),
+			None => Err("unexpected end of text".to_string())
+		}
+	}
+
+	fn parse_object(&mut self) -> Result<JsonValue, String> {
+		self.expect(b'{')?;
+		let mut fields = Vec::new();
+		if self.peek() == Some(b'}') {
+			self.position += 1;
+			return Ok(JsonValue::Object(fields));
+		}
+		loop {
+			self.skip_whitespace();
+			let key = self.parse_string()?;
+			self.expect(b':')?;
+			fields.push((key, self.parse_value()?));
+			match self.peek() {
+				Some(b',') => self.position += 1,
+				Some(b'}') => {
+					self.position += 1;
+					return Ok(JsonValue::Object(fields));
+				},
+				_ => return Err(format!("expected ',' or '}}' at byte {}", self.position))
+			}
+		}
+	}
+
+	fn parse_array(&mut self) -> Result<JsonValue, String> {
+		self.expect(b'[')?;
+		let mut values = Vec::new();
+		if self.peek() == Some(b']') {
+			self.position += 1;
+			return Ok(JsonValue::Array(values));
+		}
+		loop {
+			values.push(self.parse_value()?);
+			match self.peek() {
+				Some(b',') => self.position += 1,
+				Some(b']') => {
+					self.position += 1;
+					return Ok(JsonValue::Array(values));
+				},
+				_ => return Err(format!("expected ',' or ']' at byte {}", self.position))
+			}
+		}
+	}
+
+	// genome files only contain plain identifiers, so escapes other than \" and \\ are rejected
+	fn parse_string(&mut self) -> Result<String, String> {
+		self.expect(b'"')?;
+		let mut string = Vec::new();
+		while let Some(&byte) = self.text.get(self.position) {
+			self.position += 1;
+			match byte {
+				b'"' => return String::from_utf8(string).map_err(|_| "invalid utf-8 in string".to_string()),
+				b'\\' => match self.text.get(self.position) {
+					Some(&escaped) if escaped == b'"' || escaped == b'\\' => {
+						string.push(escaped);
+						self.position += 1;
+					},
+					_ => return Err(format!("unsupported escape at byte {}", self.position))
+				},
+				_ => string.push(byte)
+			}
+		}
+		Err("unterminated string".to_string())
+	}
+
+	fn parse_number(&mut self) -> Result<JsonValue, String> {
+		let start = self.position;
+		while self.position < self.text.len() && matches!(self.text[self.position], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') {
+			self.position += 1;
+		}
+		std::str::from_utf8(&self.text[start..self.position]).unwrap()
+			.parse()
+			.map(JsonValue::Number)
+			.map_err(|_| format!("invalid number at byte {}", start))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::solver::neat::tests::{seeded_neat, xor_fitness};
+	use crate::solver::neat::ParallelEvaluator;
+
+	fn node(innovation: usize, node_type: Type) -> Node {
+		Node {
+			innovation,
+			node_type,
+			activation: node_type.default_activation()
+		}
+	}
+
+	fn connection(innovation: usize, input: usize, output: usize) -> Connection {
+		Connection {
+			innovation,
+			input,
+			output,
+			weight: 0.5,
+			enabled: true
+		}
+	}
+
+	// one sensor and one output, with two hidden nodes that feed each other
+	fn looped_network(recurrent: bool) -> Network {
+		Network {
+			node_genes: vec![
+				node(0, Type::Sensor),
+				node(1, Type::Bias),
+				node(2, Type::Output),
+				node(3, Type::Hidden),
+				node(4, Type::Hidden)
+			],
+			connection_genes: vec![
+				connection(0, 0, 3),
+				connection(1, 3, 4),
+				connection(2, 4, 3),
+				connection(3, 4, 2)
+			],
+			num_sensors: 1,
+			num_outputs: 1,
+			recurrent
+		}
+	}
+
+	fn evolved_network() -> Network {
+		let mut neat = seeded_neat(5);
+		for _ in 0..10 {
+			neat.next_generation(&mut ParallelEvaluator(xor_fitness));
+		}
+		neat.get_champion().unwrap().clone()
+	}
+
+	#[test]
+	fn json_round_trip() {
+		for network in [evolved_network(), looped_network(true)] {
+			let json = network.to_json();
+			let loaded = Network::from_json(&json).unwrap();
+			assert_eq!(loaded.to_json(), json);
+			assert_eq!(loaded.to_bytes(), network.to_bytes());
+		}
+	}
+
+	#[test]
+	fn binary_round_trip() {
+		for network in [evolved_network(), looped_network(true)] {
+			let bytes = network.to_bytes();
+			let loaded = Network::from_bytes(&bytes).unwrap();
+			assert_eq!(loaded.to_bytes(), bytes);
+			assert_eq!(loaded.to_json(), network.to_json());
+		}
+	}
+
+	#[test]
+	fn rejects_dangling_node_reference() {
+		let mut network = looped_network(true);
+		network.connection_genes.push(connection(4, 3, 9));
+		assert!(matches!(Network::from_json(&network.to_json()), Err(GenomeError::Invalid(_))));
+		assert!(matches!(Network::from_bytes(&network.to_bytes()), Err(GenomeError::Invalid(_))));
+	}
+
+	#[test]
+	fn rejects_cycle_unless_recurrent() {
+		let network = looped_network(false);
+		assert!(matches!(Network::from_json(&network.to_json()), Err(GenomeError::Invalid(_))));
+		assert!(matches!(Network::from_bytes(&network.to_bytes()), Err(GenomeError::Invalid(_))));
+
+		let mut network = looped_network(false);
+		network.connection_genes[2].enabled = false;
+		assert!(Network::from_json(&network.to_json()).is_ok());
+	}
+
+	#[test]
+	fn rejects_wrong_sensor_count() {
+		let mut network = looped_network(true);
+		network.num_sensors = 2;
+		assert!(matches!(Network::from_json(&network.to_json()), Err(GenomeError::Invalid(_))));
+
+		let path = std::env::temp_dir().join(format!("neat_genome_test_{}.json", std::process::id()));
+		looped_network(true).save_json(&path).unwrap();
+		let loaded = Network::load(&path, 2, 1);
+		fs::remove_file(&path).unwrap();
+		assert!(matches!(loaded, Err(GenomeError::Invalid(_))));
+	}
+
+	#[test]
+	fn rejects_malformed_json() {
+		let json = looped_network(true).to_json();
+		assert!(matches!(Network::from_json(&json[..json.len() / 2]), Err(GenomeError::Parse(_))));
+		assert!(matches!(Network::from_json(&json.replace("hidden", "mystery")), Err(GenomeError::Parse(_))));
+	}
+}
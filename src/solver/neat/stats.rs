@@ -0,0 +1,175 @@
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::Neat;
+
+// a summary of one evaluated generation, taken before fitness sharing
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenerationStats {
+	pub generation: usize,
+	pub best_fitness: f32,
+	pub mean_fitness: f32,
+	pub median_fitness: f32,
+	pub num_species: usize,
+	pub species_sizes: Vec<usize>,
+	pub speciation_distance: f64,
+	pub mean_nodes: f32,
+	pub mean_connections: f32, // only enabled connections are counted
+	pub max_nodes: usize,
+	pub max_connections: usize,
+	pub node_innovations: usize,
+	pub connection_innovations: usize
+}
+
+impl GenerationStats {
+	pub(crate) fn collect(neat: &Neat) -> GenerationStats {
+		let population_size = neat.population.len().max(1) as f32;
+
+		// a nan from the evaluator would make every summary nan, so those networks are left out
+		let mut fitnesses: Vec<f32> = neat.network_fitnesses.iter().copied().filter(|f| !f.is_nan()).collect();
+		fitnesses.sort_by(f32::total_cmp);
+		let median_fitness = match fitnesses.len() {
+			0 => 0.0,
+			n if n % 2 == 0 => (fitnesses[n / 2 - 1] + fitnesses[n / 2]) / 2.0,
+			n => fitnesses[n / 2]
+		};
+
+		let mut total_nodes = 0;
+		let mut total_connections = 0;
+		let mut max_nodes = 0;
+		let mut max_connections = 0;
+		for network in &neat.population {
+			let nodes = network.node_genes.len();
+			let connections = network.connection_genes.iter().filter(|c| c.enabled).count();
+			total_nodes += nodes;
+			total_connections += connections;
+			max_nodes = max_nodes.max(nodes);
+			max_connections = max_connections.max(connections);
+		}
+
+		GenerationStats {
+			generation: neat.generation,
+			best_fitness: fitnesses.last().copied().unwrap_or(0.0),
+			mean_fitness: fitnesses.iter().sum::<f32>() / fitnesses.len().max(1) as f32,
+			median_fitness,
+			num_species: neat.species.len(),
+			species_sizes: neat.species.iter().map(|s| s.individuals.len()).collect(),
+			speciation_distance: neat.minimum_speciation_distance,
+			mean_nodes: total_nodes as f32 / population_size,
+			mean_connections: total_connections as f32 / population_size,
+			max_nodes,
+			max_connections,
+			node_innovations: neat.node_innovation,
+			connection_innovations: neat.connection_list.len()
+		}
+	}
+
+	pub fn csv_header() -> &'static str {
+		"generation,best_fitness,mean_fitness,median_fitness,num_species,species_sizes,speciation_distance,\
+		mean_nodes,mean_connections,max_nodes,max_connections,node_innovations,connection_innovations"
+	}
+
+	// species sizes are separated by semicolons so they stay in one column
+	pub fn to_csv_row(&self) -> String {
+		let sizes: Vec<String> = self.species_sizes.iter().map(|s| s.to_string()).collect();
+		format!(
+			"{},{},{},{},{},{},{},{},{},{},{},{},{}",
+			self.generation, self.best_fitness, self.mean_fitness, self.median_fitness,
+			self.num_species, sizes.join(";"), self.speciation_distance,
+			self.mean_nodes, self.mean_connections, self.max_nodes, self.max_connections,
+			self.node_innovations, self.connection_innovations
+		)
+	}
+
+	pub fn to_json(&self) -> String {
+		let mut json = String::new();
+		write!(json, "{{\"generation\":{}", self.generation).unwrap();
+		write!(json, ",\"best_fitness\":{}", json_number(self.best_fitness as f64)).unwrap();
+		write!(json, ",\"mean_fitness\":{}", json_number(self.mean_fitness as f64)).unwrap();
+		write!(json, ",\"median_fitness\":{}", json_number(self.median_fitness as f64)).unwrap();
+		write!(json, ",\"num_species\":{}", self.num_species).unwrap();
+		let sizes: Vec<String> = self.species_sizes.iter().map(|s| s.to_string()).collect();
+		write!(json, ",\"species_sizes\":[{}]", sizes.join(",")).unwrap();
+		write!(json, ",\"speciation_distance\":{}", json_number(self.speciation_distance)).unwrap();
+		write!(json, ",\"mean_nodes\":{}", json_number(self.mean_nodes as f64)).unwrap();
+		write!(json, ",\"mean_connections\":{}", json_number(self.mean_connections as f64)).unwrap();
+		write!(json, ",\"max_nodes\":{}", self.max_nodes).unwrap();
+		write!(json, ",\"max_connections\":{}", self.max_connections).unwrap();
+		write!(json, ",\"node_innovations\":{}", self.node_innovations).unwrap();
+		write!(json, ",\"connection_innovations\":{}}}", self.connection_innovations).unwrap();
+		json
+	}
+}
+
+// json has no representation for infinities or nan
+fn json_number(value: f64) -> String {
+	if value.is_finite() {value.to_string()} else {"null".to_string()}
+}
+
+// called by Neat with the stats of every generation it evaluates
+pub trait Reporter: Send {
+	fn report(&mut self, stats: &GenerationStats);
+}
+
+impl<F: FnMut(&GenerationStats) + Send> Reporter for F {
+	fn report(&mut self, stats: &GenerationStats) {
+		self(stats)
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+	Csv,
+	JsonLines
+}
+
+pub struct StatsLogger {
+	writer: BufWriter<File>,
+	format: LogFormat
+}
+
+impl StatsLogger {
+	pub fn create<P: AsRef<Path>>(path: P, format: LogFormat) -> io::Result<StatsLogger> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		if format == LogFormat::Csv {
+			writeln!(writer, "{}", GenerationStats::csv_header())?;
+		}
+		Ok(StatsLogger {
+			writer,
+			format
+		})
+	}
+
+	// adds to an existing log, e.g. when a run is resumed from a checkpoint
+	pub fn append<P: AsRef<Path>>(path: P, format: LogFormat) -> io::Result<StatsLogger> {
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+		let is_empty = file.metadata()?.len() == 0;
+		let mut writer = BufWriter::new(file);
+		if format == LogFormat::Csv && is_empty {
+			writeln!(writer, "{}", GenerationStats::csv_header())?;
+		}
+		Ok(StatsLogger {
+			writer,
+			format
+		})
+	}
+
+	// each record is flushed so the log can be read while a run is going
+	pub fn write(&mut self, stats: &GenerationStats) -> io::Result<()> {
+		match self.format {
+			LogFormat::Csv => writeln!(self.writer, "{}", stats.to_csv_row())?,
+			LogFormat::JsonLines => writeln!(self.writer, "{}", stats.to_json())?
+		}
+		self.writer.flush()
+	}
+}
+
+impl Reporter for StatsLogger {
+	fn report(&mut self, stats: &GenerationStats) {
+		if let Err(error) = self.write(stats) {
+			eprintln!("could not write generation stats: {}", error);
+		}
+	}
+}
@@ -0,0 +1,348 @@
+use super::game::{Game, COLUMN_ORDER};
+use super::transposition::{Bound, ReplacementPolicy, TranspositionTable};
+
+const CELLS: i32 = 42;
+pub const DEFAULT_TABLE_MEMORY: usize = 256 << 20;
+
+// the game theoretic value for the player to move
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameValue {
+	Win,
+	Loss,
+	Draw
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Solution {
+	// positive when the player to move wins. the sooner the win the larger the score:
+	// it is the number of pieces the winner still had in hand when playing the winning move
+	pub score: i32,
+	pub value: GameValue,
+	pub moves_to_end: usize, // plies until the game ends when both sides play perfectly
+	pub nodes: u64
+}
+
+// an exact solver, unlike Solver which searches to a fixed depth and guesses with a heuristic
+pub struct PerfectSolver {
+	table: TranspositionTable,
+	nodes: u64
+}
+
+impl PerfectSolver {
+	pub fn new() -> PerfectSolver {
+		PerfectSolver::with_table(DEFAULT_TABLE_MEMORY)
+	}
+
+	pub fn with_table(table_memory: usize) -> PerfectSolver {
+		PerfectSolver {
+			table: TranspositionTable::new(table_memory, ReplacementPolicy::AlwaysReplace),
+			nodes: 0
+		}
+	}
+
+	pub fn solve(&mut self, game: &Game) -> Solution {
+		self.nodes = 0;
+		let score = self.get_score(game, false);
+		let value = match score {
+			s if s > 0 => GameValue::Win,
+			s if s < 0 => GameValue::Loss,
+			_ => GameValue::Draw
+		};
+		Solution {
+			score,
+			value,
+			moves_to_end: get_moves_to_end(game.get_move_count(), score),
+			nodes: self.nodes
+		}
+	}
+
+	// only finds out who wins, which is much faster than finding how quickly
+	pub fn solve_weak(&mut self, game: &Game) -> GameValue {
+		self.nodes = 0;
+		match self.get_score(game, true) {
+			s if s > 0 => GameValue::Win,
+			s if s < 0 => GameValue::Loss,
+			_ => GameValue::Draw
+		}
+	}
+
+	// the column that wins fastest or loses slowest, none if the game is over
+	pub fn get_best_move(&mut self, game: &Game) -> Option<usize> {
+		if game.check_for_win() || game.check_for_tie() {
+			return None;
+		}
+		let mut best = None;
+		for column in COLUMN_ORDER {
+			if !game.can_play(column) {
+				continue;
+			}
+			let mut next = game.copy();
+			if next.play_piece(column) {
+				return Some(column);
+			}
+			let score = if next.check_for_tie() {0} else {-self.get_score(&next, false)};
+			if best.is_none_or(|(_, best_score)| score > best_score) {
+				best = Some((column, score));
+			}
+		}
+		best.map(|(column, _)| column)
+	}
+
+	// narrows the window around the score with null window searches, each of
+	// which only finds out whether the score is above or below a guess
+	fn get_score(&mut self, game: &Game, weak: bool) -> i32 {
+		let moves = game.get_move_count() as i32;
+		if game.check_for_win() {
+			return -(CELLS + 2 - moves) / 2;
+		}
+		if game.check_for_tie() {
+			return 0;
+		}
+		if game.can_win_next() {
+			return (CELLS + 1 - moves) / 2;
+		}
+		let (mut min, mut max) = if weak {(-1, 1)} else {(-(CELLS - moves) / 2, (CELLS + 1 - moves) / 2)};
+
+		self.table.new_search();
+		while min < max {
+			// guesses near 0 first, since most positions are decided late
+			let mut guess = min + (max - min) / 2;
+			if guess <= 0 && min / 2 < guess {
+				guess = min / 2;
+			}
+			else if guess >= 0 && max / 2 > guess {
+				guess = max / 2;
+			}
+
+			let score = self.negamax(game, guess, guess + 1);
+			if score <= guess {
+				max = score;
+			}
+			else {
+				min = score;
+			}
+		}
+		min
+	}
+
+	// assumes the player to move can't win immediately
+	fn negamax(&mut self, game: &Game, mut alpha: i32, mut beta: i32) -> i32 {
+		self.nodes += 1;
+		let moves = game.get_move_count() as i32;
+
+		let next = game.get_non_losing_moves();
+		if next == 0 {
+			return -(CELLS - moves) / 2;
+		}
+		if moves >= CELLS - 2 {
+			return 0;
+		}
+
+		// the opponent can't win on their next move, and we can't win on ours
+		let min = -(CELLS - 2 - moves) / 2;
+		if alpha < min {
+			alpha = min;
+			if alpha >= beta {
+				return alpha;
+			}
+		}
+		let max = (CELLS - 1 - moves) / 2;
+		if beta > max {
+			beta = max;
+			if alpha >= beta {
+				return beta;
+			}
+		}
+
+		let key = game.get_key();
+		if let Some(entry) = self.table.get(key) {
+			let score = entry.score as i32;
+			match entry.bound {
+				Bound::Upper if score < beta => {
+					beta = score;
+					if alpha >= beta {
+						return beta;
+					}
+				},
+				Bound::Lower if score > alpha => {
+					alpha = score;
+					if alpha >= beta {
+						return alpha;
+					}
+				},
+				_ => {}
+			}
+		}
+
+		// the moves that leave the most threats first, ties going to the centre
+		let mut ordered = [(0u64, 0u32); 7];
+		let mut count = 0;
+		for column in COLUMN_ORDER {
+			let move_bit = next & Game::column_mask(column);
+			if move_bit != 0 {
+				let score = game.get_threat_score(move_bit);
+				let mut i = count;
+				while i > 0 && ordered[i - 1].1 < score {
+					ordered[i] = ordered[i - 1];
+					i -= 1;
+				}
+				ordered[i] = (move_bit, score);
+				count += 1;
+			}
+		}
+
+		for &(move_bit, _) in &ordered[..count] {
+			let mut child = game.copy();
+			child.play_move(move_bit);
+			let score = -self.negamax(&child, -beta, -alpha);
+			if score >= beta {
+				self.table.store(key, score as f32, 0, Bound::Lower, None);
+				return score;
+			}
+			if score > alpha {
+				alpha = score;
+			}
+		}
+
+		self.table.store(key, alpha as f32, 0, Bound::Upper, None);
+		alpha
+	}
+}
+
+// the winner's winning move is made after 42 - 2 * score or 43 - 2 * score pieces,
+// whichever leaves it their turn
+fn get_moves_to_end(moves: usize, score: i32) -> usize {
+	if score == 0 {
+		return CELLS as usize - moves;
+	}
+	let winner_parity = if score > 0 {moves % 2} else {(moves + 1) % 2};
+	let s = score.unsigned_abs() as usize;
+	let before_win = if winner_parity == 0 {42 - 2 * s} else {43 - 2 * s};
+	before_win + 1 - moves
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::solver::game::tests::random_position;
+	use rand::prelude::*;
+	use rand::rngs::StdRng;
+
+	const TABLE_MEMORY: usize = 1 << 20;
+
+	// plain alpha beta over every move, scored the same way as the solver
+	fn brute_force(game: &Game, mut alpha: i32, beta: i32) -> i32 {
+		let moves = game.get_move_count() as i32;
+		if (0..7).any(|c| game.can_play(c) && game.copy().play_piece(c)) {
+			return (CELLS + 1 - moves) / 2;
+		}
+		if moves >= CELLS - 1 {
+			return 0;
+		}
+		for column in (0..7).filter(|&c| game.can_play(c)) {
+			let mut next = game.copy();
+			next.play_piece(column);
+			alpha = alpha.max(-brute_force(&next, -beta, -alpha));
+			if alpha >= beta {
+				break;
+			}
+		}
+		alpha
+	}
+
+	// positions the search has to work on, rather than ones won on the next move
+	fn late_positions(seed: u64) -> Vec<Game> {
+		let mut rng = StdRng::seed_from_u64(seed);
+		(0..300)
+			.filter_map(|i| random_position(&mut rng, 26 + i % 10))
+			.filter(|game| !game.can_win_next())
+			.collect()
+	}
+
+	#[test]
+	fn matches_brute_force_on_late_positions() {
+		let mut solver = PerfectSolver::with_table(TABLE_MEMORY);
+		for game in late_positions(3) {
+			let expected = brute_force(&game, -CELLS, CELLS);
+			let solution = solver.solve(&game);
+			assert_eq!(solution.score, expected);
+			let expected_value = match expected {
+				s if s > 0 => GameValue::Win,
+				s if s < 0 => GameValue::Loss,
+				_ => GameValue::Draw
+			};
+			assert_eq!(solution.value, expected_value);
+			assert_eq!(solver.solve_weak(&game), expected_value);
+		}
+	}
+
+	#[test]
+	fn best_move_keeps_the_score() {
+		let mut solver = PerfectSolver::with_table(TABLE_MEMORY);
+		for game in late_positions(4).into_iter().take(50) {
+			let score = solver.solve(&game).score;
+			let column = solver.get_best_move(&game).unwrap();
+			let mut next = game.copy();
+			if next.play_piece(column) {
+				assert_eq!(score, (CELLS + 1 - game.get_move_count() as i32) / 2);
+			}
+			else {
+				assert_eq!(-solver.solve(&next).score, score);
+			}
+		}
+	}
+
+	#[test]
+	fn solves_immediate_wins_and_finished_games() {
+		let mut solver = PerfectSolver::with_table(TABLE_MEMORY);
+		let mut game = Game::new();
+		for column in [3, 0, 3, 0, 3, 0] {
+			game.play_piece(column);
+		}
+		let solution = solver.solve(&game);
+		assert_eq!((solution.value, solution.score, solution.moves_to_end), (GameValue::Win, 18, 1));
+		assert_eq!(solver.get_best_move(&game), Some(3));
+
+		game.play_piece(3);
+		let solution = solver.solve(&game);
+		assert_eq!((solution.value, solution.moves_to_end), (GameValue::Loss, 0));
+		assert_eq!(solver.get_best_move(&game), None);
+	}
+
+	fn play_moves(moves: &str) -> Game {
+		let mut game = Game::new();
+		for column in moves.chars().map(|c| c.to_digit(10).unwrap() as usize - 1) {
+			game.play_piece(column);
+		}
+		game
+	}
+
+	// end games from Pascal Pons' connect 4 solver test set, columns numbered from 1
+	#[test]
+	fn solves_known_positions() {
+		let mut solver = PerfectSolver::with_table(TABLE_MEMORY);
+		for (moves, score) in [
+			("7422341735647741166133573473242566", 1),
+			("23163416124767223154467471272416755633", 0),
+			("65214673556155731566316327373221417", -1)
+		] {
+			assert_eq!(solver.solve(&play_moves(moves)).score, score, "{}", moves);
+		}
+	}
+
+	#[test]
+	fn moves_to_end_follows_the_score() {
+		// the empty board is a first player win with their 21st piece
+		assert_eq!(get_moves_to_end(0, 1), 41);
+		assert_eq!(get_moves_to_end(0, 0), 42);
+		for moves in 0..CELLS as usize {
+			let win_now = (CELLS + 1 - moves as i32) / 2;
+			let lose_next = -(CELLS - moves as i32) / 2;
+			assert_eq!(get_moves_to_end(moves, win_now), 1);
+			if moves + 1 < CELLS as usize {
+				assert_eq!(get_moves_to_end(moves, lose_next), 2);
+			}
+			assert_eq!(get_moves_to_end(moves, 0), CELLS as usize - moves);
+		}
+	}
+}
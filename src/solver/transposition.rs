@@ -0,0 +1,76 @@
+use std::mem;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bound {
+	Exact,
+	Lower, // the search failed high, the score is at least this
+	Upper // the search failed low, the score is at most this
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Entry {
+	key: u64,
+	pub score: f32,
+	pub depth: u32,
+	pub bound: Bound,
+	pub best_move: Option<u8>, // a column, kept small so more entries fit in the memory budget
+	age: u8 // the search that stored this entry
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReplacementPolicy {
+	AlwaysReplace,
+	// keeps the deeper of the two entries, unless the stored one is from an earlier search
+	DepthPreferred
+}
+
+// a fixed size hash table of search results. each position maps to a single slot,
+// and the full key is kept so a slot holding a different position is never mistaken for a hit
+pub struct TranspositionTable {
+	entries: Vec<Option<Entry>>,
+	policy: ReplacementPolicy,
+	age: u8
+}
+
+impl TranspositionTable {
+	pub fn new(memory_bytes: usize, policy: ReplacementPolicy) -> TranspositionTable {
+		let capacity = (memory_bytes / mem::size_of::<Option<Entry>>()).max(1);
+		TranspositionTable {
+			entries: vec![None; capacity],
+			policy,
+			age: 0
+		}
+	}
+
+	// marks the entries stored so far as stale so the replacement policy can overwrite them
+	pub fn new_search(&mut self) {
+		self.age = self.age.wrapping_add(1);
+	}
+
+	fn get_index(&self, key: u64) -> usize {
+		(key.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 16) as usize % self.entries.len()
+	}
+
+	pub fn get(&self, key: u64) -> Option<&Entry> {
+		self.entries[self.get_index(key)].as_ref().filter(|entry| entry.key == key)
+	}
+
+	pub fn store(&mut self, key: u64, score: f32, depth: u32, bound: Bound, best_move: Option<usize>) {
+		let best_move = best_move.map(|column| column as u8);
+		let index = self.get_index(key);
+		let replace = match (&self.entries[index], self.policy) {
+			(None, _) | (_, ReplacementPolicy::AlwaysReplace) => true,
+			(Some(old), ReplacementPolicy::DepthPreferred) => old.key == key || old.age != self.age || depth >= old.depth
+		};
+		if replace {
+			self.entries[index] = Some(Entry {
+				key,
+				score,
+				depth,
+				bound,
+				best_move,
+				age: self.age
+			});
+		}
+	}
+}